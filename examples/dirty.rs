@@ -54,7 +54,7 @@ fn main() -> anyhow::Result<()> {
     let mut hive = Hive::new(&hive_file, nt_hive2::HiveParseMode::NormalWithBaseBlock)?
         .with_transaction_log(hive_log1.try_into()?)?
         .with_transaction_log(hive_log2.try_into()?)?
-        .apply_logs();
+        .apply_logs()?;
     //.treat_hive_as_clean();
 
     let mut dst = File::create(path_clean)?;
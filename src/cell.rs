@@ -15,7 +15,7 @@ pub struct CellHeader {
     #[br(temp, assert(raw_size != 0))]
     raw_size: i32,
 
-    #[br(calc((raw_size as i64).abs().try_into().unwrap()),
+    #[br(calc(raw_size.unsigned_abs() as usize),
         assert(size & 0b111 == 0, "size should be aligned to 8 bytes"))]
     size: usize,
 
@@ -34,8 +34,7 @@ impl CellHeader {
     /// Returns the size of the [Cell] content, which equals to the size of the cell
     /// minus the size of its header
     pub fn contents_size(&self) -> usize {
-        assert!(self.size() >= 4);
-        self.size() - std::mem::size_of::<i32>()
+        self.size().saturating_sub(std::mem::size_of::<i32>())
     }
 
     /// returns [true] iff the [Cell] is considered as being *deleted*
@@ -67,9 +66,10 @@ impl CellHeader {
 /// # fn main() -> Result<(), Box<dyn Error>> {
 /// # let hive_file = File::open("tests/data/testhive")?;
 /// # let mut hive = Hive::new(hive_file, HiveParseMode::NormalWithBaseBlock)?;
-/// # let offset = hive.root_cell_offset();
+/// # let offset = hive.root_cell_offset()?;
+/// let ctx = hive.parse_context();
 /// hive.seek(SeekFrom::Start(offset.0.into()))?;
-/// let cell: Cell<KeyNodeWithMagic, ()> = hive.read_le().unwrap();
+/// let cell: Cell<KeyNodeWithMagic, (ParseContext,)> = hive.read_le_args((ctx,)).unwrap();
 /// let my_node: KeyNode = {
 ///     let knwm: KeyNodeWithMagic = cell.into();
 ///     knwm.into()
@@ -4,13 +4,22 @@ use crate::{CellHeader};
 #[derive_binread]
 #[br(import(count:Option<usize>))]
 pub (crate) struct CellWithU8List {
-    #[br(temp)]
     header: CellHeader,
 
-    #[br(count=count.or_else(|| Some(header.contents_size())).unwrap())]
+    #[br(count=count.unwrap_or_else(|| header.contents_size()))]
     pub data: Vec<u8>
 }
 
+impl CellWithU8List {
+    pub fn is_deleted(&self) -> bool {
+        self.header.is_deleted()
+    }
+
+    pub fn cell_size(&self) -> usize {
+        self.header.size()
+    }
+}
+
 impl From<CellWithU8List> for Vec<u8> {
     fn from(cell: CellWithU8List) -> Self {
         cell.data
@@ -0,0 +1,138 @@
+use crate::Offset;
+
+/// describes the purpose of a cell which contains (parts of) the data of a [`KeyValue`](crate::KeyValue)
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DataCellRole {
+    /// the cell contains all of the data
+    Data,
+
+    /// the cell contains the big data (`db`) record
+    BigDataHeader,
+
+    /// the cell contains the list of big data segments
+    SegmentList,
+
+    /// the cell contains one segment of big data
+    Segment,
+}
+
+/// represents a single cell which was read to obtain the data of a [`KeyValue`](crate::KeyValue)
+#[derive(Debug, Clone)]
+pub struct DataCell {
+    offset: Offset,
+    cell_size: usize,
+    is_deleted: bool,
+    role: DataCellRole,
+}
+
+impl DataCell {
+    pub(crate) fn new(
+        offset: Offset,
+        cell_size: usize,
+        is_deleted: bool,
+        role: DataCellRole,
+    ) -> Self {
+        Self {
+            offset,
+            cell_size,
+            is_deleted,
+            role,
+        }
+    }
+
+    /// Returns the offset of this cell, relative to the start of the hive bins data
+    pub fn offset(&self) -> Offset {
+        self.offset
+    }
+
+    /// Returns the size of this cell, including its header
+    pub fn cell_size(&self) -> usize {
+        self.cell_size
+    }
+
+    /// returns [true] iff the cell is considered as being *deleted*
+    pub fn is_deleted(&self) -> bool {
+        self.is_deleted
+    }
+
+    /// Returns the purpose of this cell
+    pub fn role(&self) -> DataCellRole {
+        self.role
+    }
+}
+
+/// describes a cell which could not be read completely while reading the data of a [`KeyValue`](crate::KeyValue)
+#[derive(Debug, Clone)]
+pub struct DataReadError {
+    offset: Offset,
+    role: DataCellRole,
+    message: String,
+}
+
+impl DataReadError {
+    pub(crate) fn new(offset: Offset, role: DataCellRole, message: String) -> Self {
+        Self {
+            offset,
+            role,
+            message,
+        }
+    }
+
+    /// Returns the offset of the cell which could not be read
+    pub fn offset(&self) -> Offset {
+        self.offset
+    }
+
+    /// Returns the purpose of the cell which could not be read
+    pub fn role(&self) -> DataCellRole {
+        self.role
+    }
+
+    /// Returns a description of what went wrong
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for DataReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unable to read {:?} cell at 0x{:08x}: {}",
+            self.role, self.offset.0, self.message
+        )
+    }
+}
+
+/// lists all cells which were read to obtain the data of a [`KeyValue`](crate::KeyValue).
+/// The cells are listed in the order in which they have been read.
+#[derive(Debug, Clone, Default)]
+pub struct DataProvenance {
+    cells: Vec<DataCell>,
+    errors: Vec<DataReadError>,
+}
+
+impl DataProvenance {
+    pub(crate) fn new(cells: Vec<DataCell>, errors: Vec<DataReadError>) -> Self {
+        Self { cells, errors }
+    }
+
+    /// Returns the list of cells, which is empty if the data is resident or empty
+    pub fn cells(&self) -> &Vec<DataCell> {
+        &self.cells
+    }
+
+    /// Returns the cells which contain the data bytes themselves, ignoring cells which
+    /// contain only big data management structures
+    pub fn data_cells(&self) -> impl Iterator<Item = &DataCell> {
+        self.cells
+            .iter()
+            .filter(|c| matches!(c.role, DataCellRole::Data | DataCellRole::Segment))
+    }
+
+    /// Returns the list of cells which could not be read completely. If this list is not empty,
+    /// the data of the value is incomplete.
+    pub fn errors(&self) -> &Vec<DataReadError> {
+        &self.errors
+    }
+}
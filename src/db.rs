@@ -1,48 +1,89 @@
-use binread::{derive_binread, ReadOptions, BinResult, FilePtr32};
-use std::io::{Read, Seek};
+use binread::{derive_binread, BinReaderExt, BinResult};
+use std::io::{Read, Seek, SeekFrom};
 
-use crate::{CellHeader, cell_with_u8_list::CellWithU8List};
+use crate::{CellHeader, cell_with_u8_list::CellWithU8List, data_provenance::{DataCell, DataCellRole, DataReadError}, Offset};
 
 pub const BIGDATA_MAX_SEGMENT_SIZE: u16 = 16344;
 
+/// Represents a big data record, as documented in <https://github.com/msuhanov/regf/blob/master/Windows%20registry%20file%20format%20specification.md#big-data>.
+/// This record only references the list of segments, which contain the data.
 #[derive_binread]
 #[br(magic = b"db")]
 pub struct BigData {
-    #[br(temp)]
     segments_count: u16,
+    segment_list_offset: Offset,
+}
+
+/// the data which could be collected from all segments of a big data record
+pub(crate) struct BigDataContent {
+    pub bytes: Vec<u8>,
+    pub cells: Vec<DataCell>,
+    pub errors: Vec<DataReadError>,
+}
+
+impl BigData {
+    /// reads all segments of this big data record and returns at most `data_size` bytes.
+    /// Segments which cannot be read or which are too small are reported as errors and
+    /// their missing bytes are filled with zeros, so that all remaining data stays at its offset.
+    pub(crate) fn read_content<R: Read + Seek>(&self, reader: &mut R, data_size: usize) -> BinResult<BigDataContent> {
+        reader.seek(SeekFrom::Start(self.segment_list_offset.0.into()))?;
+        let segment_list: SegmentList = reader.read_le_args((self.segments_count,))?;
+
+        // `data_size` has not been checked yet, so we allocate memory only for segments which are listed
+        let mut content = BigDataContent {
+            bytes: Vec::new(),
+            cells: vec![DataCell::new(self.segment_list_offset, segment_list.header.size(), segment_list.header.is_deleted(), DataCellRole::SegmentList)],
+            errors: Vec::new(),
+        };
+
+        let mut remaining = data_size;
+        for offset in segment_list.segments.iter() {
+            if remaining == 0 {
+                break;
+            }
+            let expected_size = remaining.min(BIGDATA_MAX_SEGMENT_SIZE.into());
+            remaining -= expected_size;
+
+            let segment: BinResult<CellWithU8List> = reader
+                .seek(SeekFrom::Start(offset.0.into()))
+                .map_err(binread::Error::from)
+                .and_then(|_| reader.read_le_args((None,)));
 
-    #[br(temp, deref_now, args(segments_count,))]
-    segments: FilePtr32<SegmentList>,
+            match segment {
+                Ok(segment) => {
+                    content.cells.push(DataCell::new(*offset, segment.cell_size(), segment.is_deleted(), DataCellRole::Segment));
+                    if segment.data.len() < expected_size {
+                        content.errors.push(DataReadError::new(*offset, DataCellRole::Segment, format!(
+                            "segment is too small: expected {expected_size} bytes, but found only {} bytes", segment.data.len())));
+                    }
+                    let size = expected_size.min(segment.data.len());
+                    content.bytes.extend(&segment.data[..size]);
+                }
+                Err(why) => {
+                    log::debug!("unable to read big data segment at 0x{:08x}: {}", offset.0, why);
+                    content.errors.push(DataReadError::new(*offset, DataCellRole::Segment, why.to_string()));
+                }
+            }
 
-    #[br(parse_with=obtain_data_bytes, args(&segments,))]
-    pub bytes: Vec<u8>
+            // keep the data of all following segments at their offsets
+            let end = data_size - remaining;
+            content.bytes.resize(end, 0);
+        }
+
+        if remaining > 0 {
+            content.errors.push(DataReadError::new(self.segment_list_offset, DataCellRole::SegmentList, format!(
+                "there are not enough segments to store {data_size} bytes")));
+        }
+
+        Ok(content)
+    }
 }
 
 #[derive_binread]
 #[br(import(count:u16))]
 struct SegmentList {
-    #[br(temp)]
     header: CellHeader,
 
-    #[br(count=count, args(None,))]
-    pub segments: Vec<FilePtr32<CellWithU8List>>
+    #[br(count=count)]
+    pub segments: Vec<Offset>
 }
-
-fn obtain_data_bytes<R: Read + Seek>(
-    _reader: &mut R,
-    _ro: &ReadOptions,
-    args: (&FilePtr32<SegmentList>,),
-) -> BinResult<Vec<u8>> {
-    let segment_list = args.0.value.as_ref().unwrap();
-
-    // allocate the maximum expected size of data
-    let mut res = Vec::with_capacity(segment_list.segments.len() * BIGDATA_MAX_SEGMENT_SIZE as usize);
-
-    for item_ptr in &segment_list.segments {
-        let item = item_ptr.value.as_ref().unwrap();
-        let data: &Vec<u8> = &item.data;
-        res.extend(data);
-    }
-    
-    Ok(res)
-}
\ No newline at end of file
@@ -0,0 +1,176 @@
+use crate::{Offset, SubkeysListError, UnsupportedVersion};
+
+/// a specialized [`Result`](std::result::Result) type for operations which can fail with an [Error]
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// errors which can occur while reading hive files and transaction logs.
+///
+/// Most variants carry the offset where the error has been detected. Offsets of
+/// structures which are stored in the hive bins data are relative to the start of
+/// the hive bins data, all other offsets are relative to the start of the file.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// a structure does not start with the expected magic number
+    #[error("unexpected magic number {found:02x?} at offset 0x{offset:08x}")]
+    BadMagic { offset: u64, found: Vec<u8> },
+
+    /// the cell at the given offset is not allocated, which means that it has been deleted
+    #[error("the cell at offset 0x{:08x} is not allocated", offset.0)]
+    DeletedCell { offset: Offset },
+
+    /// the offset points behind the end of the hive bins data
+    #[error("the offset 0x{:08x} is outside of the hive bins data", offset.0)]
+    OffsetOutOfBounds { offset: Offset },
+
+    /// the cell at the given offset is too small to contain the expected data
+    #[error("the cell at offset 0x{:08x} contains only {found} bytes, but {expected} bytes are needed", offset.0)]
+    CellTooSmall {
+        offset: Offset,
+        expected: usize,
+        found: usize,
+    },
+
+    /// a key value stores its data in the data offset field, but declares a size
+    /// of more than 4 bytes
+    #[error("invalid data size 0x{data_size:08x} at offset 0x{offset:08x}")]
+    InvalidDataSize { offset: u64, data_size: u32 },
+
+    /// the checksum of a base block does not match its contents
+    #[error(
+        "expected checksum of 0x{expected:08x}, but found 0x{found:08x} at offset 0x{offset:08x}"
+    )]
+    BadChecksum {
+        offset: u64,
+        expected: u32,
+        found: u32,
+    },
+
+    /// a hash of a transaction log entry does not match the contents of the entry
+    #[error("expected hash of 0x{expected:08x}, but found 0x{found:08x} in the transaction log entry at offset 0x{offset:08x}")]
+    LogHashMismatch {
+        offset: u64,
+        expected: u32,
+        found: u32,
+    },
+
+    /// the hive uses a format version which is not supported
+    #[error(transparent)]
+    UnsupportedVersion(#[from] UnsupportedVersion),
+
+    /// the subkeys lists of a key cannot be traversed
+    #[error(transparent)]
+    SubkeysList(#[from] SubkeysListError),
+
+    /// resolving the symbolic link of the key node at `offset` leads to a link
+    /// which has already been visited
+    #[error("symbolic link loop detected at '{target}' (key node at offset 0x{:08x})", offset.0)]
+    SymbolicLinkLoop { offset: Offset, target: String },
+
+    /// the target of the symbolic link of the key node at `offset` is not below the
+    /// mount point of the hive (see [`Hive::set_mount_point`](crate::Hive::set_mount_point)),
+    /// which means that it points into another hive
+    #[error("symbolic link to '{target}' points into another hive (key node at offset 0x{:08x})", offset.0)]
+    SymbolicLinkIntoOtherHive { offset: Offset, target: String },
+
+    /// the operation requires a base block, but the hive has none
+    #[error("this hive has no base block")]
+    MissingBaseBlock,
+
+    /// the offset of the root cell is unknown, which is the case if the hive
+    /// has been opened using [`HiveParseMode::Raw`](crate::HiveParseMode::Raw)
+    #[error("the offset of the root cell is unknown")]
+    MissingRootCell,
+
+    /// the sequence numbers of the base block do not allow to apply transaction logs
+    #[error(
+        "the difference between the sequence numbers is greater than 1: {primary} and {secondary}"
+    )]
+    SequenceNumberMismatch { primary: u32, secondary: u32 },
+
+    /// a sequence number must not be lower than the current sequence number of the base block
+    #[error(
+        "the sequence number {sequence_number} is lower than the current sequence number {current}"
+    )]
+    OutdatedSequenceNumber { sequence_number: u32, current: u32 },
+
+    /// a dirty page of a transaction log could not be placed at the given offset
+    #[error("unable to apply the dirty page at offset 0x{offset:08x}: {message}")]
+    PatchFailed { offset: u64, message: String },
+
+    /// any other violation of the file format
+    #[error("{0}")]
+    Format(binread::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl Error {
+    /// wraps this error, so that it can be returned by a [binread] parser. The error
+    /// is unwrapped again when it is converted back into an [Error].
+    pub(crate) fn into_binread_error(self, pos: u64) -> binread::Error {
+        match self {
+            Error::Format(why) => why,
+            Error::Io(why) => binread::Error::Io(why),
+            error => binread::Error::Custom {
+                pos,
+                err: Box::new(error),
+            },
+        }
+    }
+}
+
+impl From<binread::Error> for Error {
+    fn from(error: binread::Error) -> Self {
+        match error {
+            binread::Error::BadMagic { pos, found } => Error::BadMagic {
+                offset: pos,
+                found: magic_bytes(found.as_ref()),
+            },
+            binread::Error::Io(why) => Error::Io(why),
+            binread::Error::Custom { pos, err } => {
+                let err = match err.downcast::<Error>() {
+                    Ok(error) => return *error,
+                    Err(err) => err,
+                };
+                let err = match err.downcast::<UnsupportedVersion>() {
+                    Ok(version) => return Error::UnsupportedVersion(*version),
+                    Err(err) => err,
+                };
+                match err.downcast::<SubkeysListError>() {
+                    Ok(error) => Error::SubkeysList(*error),
+                    Err(err) => Error::Format(binread::Error::Custom { pos, err }),
+                }
+            }
+
+            // an enum whose variants are distinguished by their magic numbers
+            binread::Error::EnumErrors {
+                pos,
+                variant_errors,
+            } if variant_errors
+                .iter()
+                .all(|(_, e)| matches!(e, binread::Error::BadMagic { .. })) =>
+            {
+                let found = variant_errors
+                    .iter()
+                    .find_map(|(_, e)| match e {
+                        binread::Error::BadMagic { found, .. } => Some(magic_bytes(found.as_ref())),
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                Error::BadMagic { offset: pos, found }
+            }
+            error => Error::Format(error),
+        }
+    }
+}
+
+fn magic_bytes(found: &(dyn std::any::Any + Send + Sync)) -> Vec<u8> {
+    if let Some(found) = found.downcast_ref::<[u8; 2]>() {
+        found.to_vec()
+    } else if let Some(found) = found.downcast_ref::<[u8; 4]>() {
+        found.to_vec()
+    } else {
+        Vec::new()
+    }
+}
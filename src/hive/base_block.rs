@@ -2,15 +2,30 @@ use std::io::Write;
 
 use binread::{count, BinRead};
 use binwrite::{write_track::WriteTrack, BinWrite, WriterOption};
+use bitflags::bitflags;
 use byteorder::{LittleEndian, WriteBytesExt};
+use chrono::{DateTime, Utc};
 use derive_getters::Getters;
+use winstructs::guid::Guid;
 
-use crate::Offset;
+use crate::util::filetime_to_datetime;
+use crate::{Error, Offset, Result};
 
 use super::FileType;
 
 pub const BASEBLOCK_SIZE: usize = 4096;
 
+/// the newest minor version of the hive format known to this crate
+pub(crate) const MAX_MINOR_VERSION: u32 = 6;
+
+/// is returned as [`Error::UnsupportedVersion`] if a hive uses a format version which is not supported
+#[derive(thiserror::Error, Debug, Clone, Copy, Eq, PartialEq)]
+#[error("unsupported hive format version {major}.{minor}")]
+pub struct UnsupportedVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
 #[derive(Default, Debug, Clone)]
 struct CalculatedChecksum(u32);
 
@@ -56,7 +71,7 @@ pub struct HiveBaseBlock {
     /// accessible by BinWrite
     ///
     /// Offset: 0x0000
-    #[br(assert(magic == "regf".as_bytes()))]
+    #[br(assert(magic == "regf".as_bytes(), Error::BadMagic { offset: 0, found: magic.to_vec() }))]
     magic: [u8; 4],
 
     /// This number is incremented by 1 in the beginning of a write operation on the primary file
@@ -77,13 +92,13 @@ pub struct HiveBaseBlock {
     /// Major version of a hive writer
     ///
     /// Offset: 0x0014
-    #[br(assert(major_version==1))]
     major_version: u32,
 
-    /// Minor version of a hive writer
+    /// Minor version of a hive writer. The version is validated only after both
+    /// version fields have been read, so that errors contain the full version
     ///
     /// Offset: 0x0018
-    #[br(assert([3, 4, 5, 6].contains(&minor_version)))]
+    #[br(assert(major_version==1 && minor_version <= MAX_MINOR_VERSION, UnsupportedVersion{major: major_version, minor: minor_version}))]
     minor_version: u32,
 
     /// 0 means primary file
@@ -107,7 +122,7 @@ pub struct HiveBaseBlock {
     /// Size of the hive bins data in bytes
     ///
     /// Offset: 0x0028
-    #[br(assert(data_size%4096 == 0, "actual value is {data_size}"))]
+    #[br(assert(data_size.is_multiple_of(4096), "actual value is {data_size}"))]
     data_size: u32,
 
     /// Logical sector size of the underlying disk in bytes divided by 512
@@ -117,20 +132,106 @@ pub struct HiveBaseBlock {
 
     /// UTF-16LE string (contains a partial file path to the primary file, or a
     /// file name of the primary file), used for debugging purposes
+    ///
+    /// Offset: 0x0030
     #[br(count = 32)]
+    #[getter(skip)]
     file_name: Vec<u16>,
 
-    #[br(count = 99)]
+    /// GUID of the resource manager (only valid if the GUID signature is `rmtm`)
+    ///
+    /// Offset: 0x0070
+    #[getter(skip)]
+    rm_id: [u8; 16],
+
+    /// GUID used to generate a file name of a log file stream for the transaction manager
+    /// (only valid if the GUID signature is `rmtm`)
+    ///
+    /// Offset: 0x0080
+    #[getter(skip)]
+    log_id: [u8; 16],
+
+    /// Offset: 0x0090
+    #[getter(skip)]
+    flags: u32,
+
+    /// GUID used to generate a file name of a transaction manager
+    /// (only valid if the GUID signature is `rmtm`)
+    ///
+    /// Offset: 0x0094
+    #[getter(skip)]
+    tm_id: [u8; 16],
+
+    /// ASCII string `rmtm`, if RmId, LogId and TmId are valid
+    ///
+    /// Offset: 0x00a4
+    guid_signature: [u8; 4],
+
+    /// FILETIME (UTC), the lowest 2 bits contain the type of the last reorganization
+    ///
+    /// Offset: 0x00a8
+    #[getter(skip)]
+    last_reorganized_timestamp: u64,
+
+    /// ASCII string `OfRg`, if this hive has been written by the offline registry library
+    ///
+    /// Offset: 0x00b0
+    #[getter(skip)]
+    offline_registry_signature: [u8; 4],
+
+    /// Offset: 0x00b4
+    #[getter(skip)]
+    offline_registry_flags: u32,
+
+    #[br(count = 81)]
+    #[getter(skip)]
     padding_1: Vec<u32>,
 
     /// XOR-32 checksum of the previous 508 bytes
-    #[br(assert(calculated_checksum.as_ref() == &checksum, "expected checksum of 0x{:08x}, but found 0x{checksum:08x} instead", calculated_checksum.as_ref()))]
+    #[br(assert(calculated_checksum.as_ref() == &checksum, Error::BadChecksum { offset: 0x1fc, expected: *calculated_checksum.as_ref(), found: checksum }))]
     pub checksum: u32,
 
+    /// FILETIME (UTC), only valid if this hive has been written by the offline registry library.
+    /// Read only if this is not a transaction log file
+    ///
+    /// Offset: 0x0200
+    #[br(if(file_type == FileType::HiveFile))]
+    #[binwrite(with(write_opt_u64))]
+    #[getter(skip)]
+    serialization_timestamp: Option<u64>,
+
     /// RESERVED, read only if this is not a transaction log file
-    #[br(count = 0x37E, if(file_type == FileType::HiveFile))]
+    #[br(count = 0x370, if(file_type == FileType::HiveFile))]
+    #[getter(skip)]
     padding_2: Vec<u32>,
 
+    /// GUID, which is used to restore the RmId after thawing a hive.
+    /// Read only if this is not a transaction log file
+    ///
+    /// Offset: 0x0fc8
+    #[br(if(file_type == FileType::HiveFile))]
+    #[binwrite(with(write_opt_guid))]
+    #[getter(skip)]
+    thaw_tm_id: Option<[u8; 16]>,
+
+    /// GUID, which is used to restore the RmId after thawing a hive.
+    /// Read only if this is not a transaction log file
+    ///
+    /// Offset: 0x0fd8
+    #[br(if(file_type == FileType::HiveFile))]
+    #[binwrite(with(write_opt_guid))]
+    #[getter(skip)]
+    thaw_rm_id: Option<[u8; 16]>,
+
+    /// GUID, which is used to restore the LogId after thawing a hive.
+    /// Read only if this is not a transaction log file
+    ///
+    /// Offset: 0x0fe8
+    #[br(if(file_type == FileType::HiveFile))]
+    #[binwrite(with(write_opt_guid))]
+    #[getter(skip)]
+    thaw_log_id: Option<[u8; 16]>,
+
     /// This field has no meaning on a disk, read only if this is not a transaction log file
     #[br(if(file_type == FileType::HiveFile))]
     #[binwrite(with(write_opt_u32))]
@@ -153,14 +254,179 @@ fn write_opt_u32<W: Write>(
     }
 }
 
+fn write_opt_u64<W: Write>(
+    value: &std::option::Option<u64>,
+    write_track: &mut WriteTrack<&mut W>,
+    _option: &&WriterOption,
+) -> std::result::Result<(), std::io::Error> {
+    write_track.write_u64::<LittleEndian>(value.unwrap_or(0))
+}
+
+fn write_opt_guid<W: Write>(
+    value: &std::option::Option<[u8; 16]>,
+    write_track: &mut WriteTrack<&mut W>,
+    _option: &&WriterOption,
+) -> std::result::Result<(), std::io::Error> {
+    write_track.write_all(&value.unwrap_or_default())
+}
+
+fn guid_from_bytes(raw: &[u8; 16]) -> Guid {
+    Guid::new(
+        u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]),
+        u16::from_le_bytes([raw[4], raw[5]]),
+        u16::from_le_bytes([raw[6], raw[7]]),
+        [raw[8], raw[9], raw[10], raw[11], raw[12], raw[13], raw[14], raw[15]],
+    )
+}
+
+fn timestamp_if_set(raw: u64) -> Option<DateTime<Utc>> {
+    if raw == 0 {
+        None
+    } else {
+        Some(filetime_to_datetime(&raw.to_le_bytes()))
+    }
+}
+
+bitflags! {
+    /// flags of a [HiveBaseBlock]
+    pub struct BaseBlockFlags: u32 {
+        /// KTM locked the hive (there are pending or anticipated transactions)
+        const KTM_LOCKED = 0x0000_0001;
+
+        /// The hive has been defragmented (all its pages are dirty therefore)
+        /// and it is being written to a disk (Windows 8 and Windows Server 2012 only)
+        const DEFRAGMENTED = 0x0000_0002;
+    }
+}
+
+/// type of the last reorganization of a hive, which is stored in the
+/// lowest 2 bits of the last reorganized timestamp
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ReorganizationType {
+    /// the hive has been defragmented
+    Defragmented,
+
+    /// the access bits of all key nodes have been cleared
+    AccessBitsCleared,
+}
+
 impl HiveBaseBlock {
+    /// Returns the file name stored in the base block, which is a partial path
+    /// to the primary file (used for debugging purposes)
+    pub fn file_name(&self) -> String {
+        let len = self
+            .file_name
+            .iter()
+            .position(|c| *c == 0)
+            .unwrap_or(self.file_name.len());
+        String::from_utf16_lossy(&self.file_name[..len])
+    }
+
+    /// Returns the file name as it is stored in the base block
+    pub fn raw_file_name(&self) -> &Vec<u16> {
+        &self.file_name
+    }
+
+    /// Returns the GUID of the resource manager
+    pub fn rm_id(&self) -> Guid {
+        guid_from_bytes(&self.rm_id)
+    }
+
+    /// Returns the GUID used to generate a file name of a log file stream for the transaction manager
+    pub fn log_id(&self) -> Guid {
+        guid_from_bytes(&self.log_id)
+    }
+
+    /// Returns the GUID used to generate a file name of a transaction manager
+    pub fn tm_id(&self) -> Guid {
+        guid_from_bytes(&self.tm_id)
+    }
+
+    /// returns [true] if the GUID signature is `rmtm`, which means that
+    /// RmId, LogId and TmId are valid
+    pub fn has_valid_guids(&self) -> bool {
+        &self.guid_signature == b"rmtm"
+    }
+
+    /// Returns the flags of this base block
+    pub fn flags(&self) -> BaseBlockFlags {
+        BaseBlockFlags::from_bits_truncate(self.flags)
+    }
+
+    /// Returns the flags field as it is stored in the base block
+    pub fn raw_flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Returns the timestamp of the last reorganization of this hive (Windows 8.1 and later),
+    /// or [`None`] if the hive has never been reorganized
+    pub fn last_reorganized_timestamp(&self) -> Option<DateTime<Utc>> {
+        timestamp_if_set(self.last_reorganized_timestamp & !0b11)
+    }
+
+    /// Returns the type of the last reorganization of this hive
+    pub fn last_reorganization_type(&self) -> Option<ReorganizationType> {
+        match self.last_reorganized_timestamp & 0b11 {
+            1 => Some(ReorganizationType::Defragmented),
+            2 => Some(ReorganizationType::AccessBitsCleared),
+            _ => None,
+        }
+    }
+
+    /// Returns the last reorganized timestamp field as it is stored in the base block,
+    /// including the reorganization type
+    pub fn raw_last_reorganized_timestamp(&self) -> u64 {
+        self.last_reorganized_timestamp
+    }
+
+    /// returns [true] if this hive has been written by the offline registry library
+    /// (which is the case for hives in Windows installation images, for example)
+    pub fn is_offline_registry(&self) -> bool {
+        &self.offline_registry_signature == b"OfRg"
+    }
+
+    /// Returns the flags of the offline registry library, if this hive has been
+    /// written by the offline registry library
+    pub fn offline_registry_flags(&self) -> Option<u32> {
+        self.is_offline_registry().then_some(self.offline_registry_flags)
+    }
+
+    /// Returns the time when this hive has been serialized by the offline registry library
+    pub fn serialization_timestamp(&self) -> Option<DateTime<Utc>> {
+        if self.offline_registry_flags() == Some(1) {
+            self.serialization_timestamp.and_then(timestamp_if_set)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the GUID which is used to restore the TmId after thawing the hive
+    pub fn thaw_tm_id(&self) -> Option<Guid> {
+        self.thaw_tm_id.as_ref().map(guid_from_bytes)
+    }
+
+    /// Returns the GUID which is used to restore the RmId after thawing the hive
+    pub fn thaw_rm_id(&self) -> Option<Guid> {
+        self.thaw_rm_id.as_ref().map(guid_from_bytes)
+    }
+
+    /// Returns the GUID which is used to restore the LogId after thawing the hive
+    pub fn thaw_log_id(&self) -> Option<Guid> {
+        self.thaw_log_id.as_ref().map(guid_from_bytes)
+    }
+
     pub fn is_dirty(&self) -> bool {
         self.primary_sequence_number != self.secondary_sequence_number
     }
 
-    pub fn set_sequence_number(&mut self, sequence_number: u32) {
-        assert!(sequence_number >= self.primary_sequence_number);
-        assert!(sequence_number >= self.secondary_sequence_number);
+    pub fn set_sequence_number(&mut self, sequence_number: u32) -> Result<()> {
+        let current = self.primary_sequence_number.max(self.secondary_sequence_number);
+        if sequence_number < current {
+            return Err(Error::OutdatedSequenceNumber {
+                sequence_number,
+                current,
+            });
+        }
 
         // patch out the old sequence numbers
         self.checksum ^= (!self.primary_sequence_number) ^ (!self.secondary_sequence_number);
@@ -170,5 +436,6 @@ impl HiveBaseBlock {
 
         // add the new sequence numbers to the checksum
         self.checksum ^= (self.primary_sequence_number) ^ (self.secondary_sequence_number);
+        Ok(())
     }
 }
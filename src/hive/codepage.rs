@@ -0,0 +1,118 @@
+use encoding_rs::{
+    Encoding, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252, WINDOWS_1253, WINDOWS_1254, WINDOWS_1255,
+    WINDOWS_1256, WINDOWS_1257, WINDOWS_1258,
+};
+
+/// Specifies how names which are stored as (extended) ASCII are decoded. This
+/// affects key names with [`KEY_COMP_NAME`](crate::KeyNodeFlags::KEY_COMP_NAME) and
+/// value names with `VALUE_COMP_NAME`, as well as strings which are not valid UTF-16.
+///
+/// Windows stores such names as Latin-1, which is the default. Some older systems
+/// stored them using the system codepage instead.
+///
+/// Use [`Hive::set_codepage`](crate::Hive::set_codepage) to select the codepage
+/// of a hive.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum Codepage {
+    /// ISO-8859-1, where every byte is mapped to the unicode character with the same value
+    #[default]
+    Latin1,
+
+    /// Central and Eastern European
+    Windows1250,
+
+    /// Cyrillic
+    Windows1251,
+
+    /// Western European
+    Windows1252,
+
+    /// Greek
+    Windows1253,
+
+    /// Turkish
+    Windows1254,
+
+    /// Hebrew
+    Windows1255,
+
+    /// Arabic
+    Windows1256,
+
+    /// Baltic
+    Windows1257,
+
+    /// Vietnamese
+    Windows1258,
+}
+
+impl Codepage {
+    /// decodes `bytes`. Bytes which are not defined in this codepage are
+    /// replaced by the unicode replacement character.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self.encoding() {
+            None => bytes.iter().map(|b| char::from(*b)).collect(),
+            Some(encoding) => encoding.decode_without_bom_handling(bytes).0.into_owned(),
+        }
+    }
+
+    /// encodes `name` using this codepage, or returns [`None`] if `name` contains
+    /// characters which are not defined in this codepage
+    pub(crate) fn encode(&self, name: &str) -> Option<Vec<u8>> {
+        match self.encoding() {
+            None => name.chars().map(|c| u8::try_from(c).ok()).collect(),
+            Some(encoding) => {
+                let (bytes, _, had_errors) = encoding.encode(name);
+                (!had_errors).then(|| bytes.into_owned())
+            }
+        }
+    }
+
+    fn encoding(&self) -> Option<&'static Encoding> {
+        match self {
+            Codepage::Latin1 => None,
+            Codepage::Windows1250 => Some(WINDOWS_1250),
+            Codepage::Windows1251 => Some(WINDOWS_1251),
+            Codepage::Windows1252 => Some(WINDOWS_1252),
+            Codepage::Windows1253 => Some(WINDOWS_1253),
+            Codepage::Windows1254 => Some(WINDOWS_1254),
+            Codepage::Windows1255 => Some(WINDOWS_1255),
+            Codepage::Windows1256 => Some(WINDOWS_1256),
+            Codepage::Windows1257 => Some(WINDOWS_1257),
+            Codepage::Windows1258 => Some(WINDOWS_1258),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Codepage::Latin1.decode(b"abc"), "abc");
+        assert_eq!(
+            Codepage::Latin1.decode(&[0xe4, 0xa4, 0x80]),
+            "\u{e4}\u{a4}\u{80}"
+        );
+
+        // 0xa4 is the euro sign in ISO-8859-15, but the currency sign in Latin-1
+        assert_eq!(Codepage::Latin1.decode(&[0xa4]), "¤");
+        assert_eq!(Codepage::Windows1252.decode(&[0x80]), "€");
+
+        assert_eq!(Codepage::Windows1250.decode(&[0x8a, 0xe8]), "Šč");
+        assert_eq!(Codepage::Windows1251.decode(&[0xcf, 0xf0, 0xe8]), "При");
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(Codepage::Latin1.encode("äöü"), Some(vec![0xe4, 0xf6, 0xfc]));
+        assert_eq!(Codepage::Latin1.encode("€"), None);
+        assert_eq!(Codepage::Windows1252.encode("€"), Some(vec![0x80]));
+        assert_eq!(
+            Codepage::Windows1251.encode("дць"),
+            Some(vec![0xe4, 0xf6, 0xfc])
+        );
+        assert_eq!(Codepage::Windows1251.encode("ä"), None);
+    }
+}
@@ -6,7 +6,7 @@ use std::{
 
 use binread::BinReaderExt;
 
-use crate::{hivebin::HiveBin, CleanHive, Hive};
+use crate::{hivebin::HiveBin, CleanHive, Error, Hive};
 
 pub(crate) struct HiveBinIterator<B>
 where
@@ -22,8 +22,22 @@ where
     B: BinReaderExt,
 {
     fn from(hive: Hive<B, CleanHive>) -> Self {
-        let hive = Rc::new(RefCell::new(hive));
-        let end_of_file = hive.borrow_mut().seek(SeekFrom::End(0)).unwrap();
+        Self::from(Rc::new(RefCell::new(hive)))
+    }
+}
+
+impl<B> From<Rc<RefCell<Hive<B, CleanHive>>>> for HiveBinIterator<B>
+where
+    B: BinReaderExt,
+{
+    fn from(hive: Rc<RefCell<Hive<B, CleanHive>>>) -> Self {
+        let end_of_file = match hive.borrow_mut().seek(SeekFrom::End(0)) {
+            Ok(end_of_file) => end_of_file,
+            Err(why) => {
+                log::warn!("unable to determine the size of the hive: {why}");
+                0
+            }
+        };
         Self {
             hive,
 
@@ -53,23 +67,31 @@ where
             HiveBin header. Because we do not know if all the cells in the hive
             have been read, we explicitely seek to the beginning of the next hivebin
             */
-            let current_start = self
+            let current_start = match self
             .hive
             .borrow_mut()
-            .seek(SeekFrom::Start(self.expected_end))
-            .unwrap();
+            .seek(SeekFrom::Start(self.expected_end)) {
+                Ok(current_start) => current_start,
+                Err(why) => {
+                    log::warn!("unable to seek to the next hivebin: {why}");
+                    return None;
+                }
+            };
 
             match HiveBin::new(Rc::clone(&self.hive)) {
                 Ok(hivebin) => {
                     self.expected_end = current_start + *hivebin.size() as u64;
-                    assert_eq!(self.expected_end & 0xfff, 0, "hivebins must be aligned at 4k boundaries");
-    
+                    if self.expected_end & 0xfff != 0 {
+                        log::warn!("the hivebin at 0x{current_start:08x} ends at 0x{:08x}, which is not aligned at a 4k boundary", self.expected_end);
+                        return None;
+                    }
+
                     log::trace!("found new hivebin at 0x{current_start:08x} with length {}, ending at 0x{:08x}",
                         hivebin.size(), self.expected_end);
                     return Some(hivebin)
                 }
                 Err(why) => {
-                    if let binread::Error::Io(kind) = &why {
+                    if let Error::Io(kind) = &why {
                         if kind.kind() == ErrorKind::UnexpectedEof {
                             return None;
                         }
@@ -1,5 +1,7 @@
 use crate::Offset;
 
+use super::HiveBaseBlock;
+
 
 pub enum HiveParseMode {
     /// to be used only when converting this hive to an iterator
@@ -9,5 +11,11 @@ pub enum HiveParseMode {
     Normal(Offset),
 
     /// for normal parsing of registry files
-    NormalWithBaseBlock
+    NormalWithBaseBlock,
+
+    /// for parsing of registry files with a possibly damaged base block. If the base block
+    /// cannot be read, the given backup copy of the base block (e.g. taken from a transaction log,
+    /// see [`TransactionLog::base_block`](crate::transactionlog::TransactionLog::base_block))
+    /// is used instead. If there is no backup, the root cell is searched in the hive bins data.
+    NormalWithBaseBlockRecovery(Option<Box<HiveBaseBlock>>),
 }
\ No newline at end of file
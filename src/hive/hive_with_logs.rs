@@ -1,11 +1,10 @@
 use std::{collections::BTreeMap, marker::PhantomData};
 
-use anyhow::bail;
 use binread::BinReaderExt;
 
 use crate::{
     transactionlog::{ApplicationResult, TransactionLog, TransactionLogsEntry},
-    Hive,
+    Error, Hive, Result,
 };
 
 use super::{BaseBlock, CleanHive, DirtyHive, HiveBaseBlock};
@@ -15,9 +14,9 @@ where
     Self: Sized,
     B: BinReaderExt,
 {
-    fn with_transaction_log(self, log: TransactionLog) -> anyhow::Result<HiveWithLogs<B, Self>> {
+    fn with_transaction_log(self, log: TransactionLog) -> Result<HiveWithLogs<B, Self>> {
         let mut transaction_logs = BTreeMap::new();
-        let base_block = self.base_block().expect("hive has no base block");
+        let base_block = self.base_block().ok_or(Error::MissingBaseBlock)?;
         if !base_block.is_dirty() {
             /*
             If a hive isn't dirty, but a transaction log file (new format) contains subsequent log entries, they are ignored.
@@ -29,8 +28,11 @@ where
             let primary_sequence_number = *base_block.primary_sequence_number();
             let secondary_sequence_number = *base_block.secondary_sequence_number();
 
-            if primary_sequence_number != secondary_sequence_number + 1 {
-                bail!("the difference between the sequence numbers is greater than 1: {primary_sequence_number} and {secondary_sequence_number}");
+            if primary_sequence_number != secondary_sequence_number.wrapping_add(1) {
+                return Err(Error::SequenceNumberMismatch {
+                    primary: primary_sequence_number,
+                    secondary: secondary_sequence_number,
+                });
             }
 
             for entry in log {
@@ -99,7 +101,7 @@ where
     B: BinReaderExt,
     C: ContainsHive<B>,
 {
-    pub fn apply_logs(self) -> Hive<B, CleanHive> {
+    pub fn apply_logs(self) -> Result<Hive<B, CleanHive>> {
         let (mut hive, logs) = self.dissolve();
 
         log::info!("trying to apply {} entries: ", logs.len());
@@ -109,10 +111,10 @@ where
                 "found entry for sequence number {}",
                 entry.sequence_number()
             );
-            if ApplicationResult::Applied != hive.apply_transaction_log(entry) {
+            if ApplicationResult::Applied != hive.apply_transaction_log(entry)? {
                 break;
             }
         }
-        hive.treat_hive_as_clean()
+        Ok(hive.treat_hive_as_clean())
     }
 }
\ No newline at end of file
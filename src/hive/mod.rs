@@ -1,29 +1,39 @@
 mod base_block;
+mod codepage;
 mod file_type;
 mod hive_bin_iterator;
 mod hive_parse_mode;
 mod hive_status;
 mod hive_with_logs;
 mod offset;
+mod parse_context;
 
 pub use base_block::*;
+pub use codepage::*;
 pub use file_type::*;
 pub(crate) use hive_bin_iterator::*;
 pub use hive_parse_mode::*;
 pub use hive_status::*;
 pub use hive_with_logs::*;
 pub use offset::*;
+pub use parse_context::*;
 
-use crate::hivebin::{CellContent, HiveBin};
+use crate::hivebin::{CellContent, HiveBin, HiveBinHeader};
+use crate::util::filetime_to_datetime;
 use crate::nk::KeyNode;
 use crate::nk::{KeyNodeFlags, KeyNodeWithMagic};
+use crate::vk::{KeyValue, KeyValueWithMagic};
+use crate::NameComparison;
 use crate::transactionlog::{ApplicationResult, TransactionLogsEntry};
 use crate::Cell;
-use anyhow::{anyhow, bail};
-use binread::{BinRead, BinReaderExt, BinResult};
+use crate::{Error, Result};
+use binread::{BinRead, BinReaderExt};
 use binwrite::BinWrite;
 use memoverlay::MemOverlay;
-use std::collections::BTreeMap;
+use chrono::{DateTime, Utc};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::rc::Rc;
 use std::io::{self, Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
 use std::marker::PhantomData;
 
@@ -55,6 +65,9 @@ where
     }
 }
 
+/// the default value of [`Hive::max_subkeys_list_depth`]
+pub const DEFAULT_MAX_SUBKEYS_LIST_DEPTH: usize = 8;
+
 /// Represents a registry hive file.
 ///
 /// Because most offsets in a registry hive file are relative to the start of the hive bins data,
@@ -72,8 +85,15 @@ where
 {
     pub data: MemOverlay<B>,
     pub(crate) base_block: Option<HiveBaseBlock>,
+    base_block_error: Option<String>,
+    first_hbin_timestamp: Option<u64>,
+    hivebin_table: Option<Vec<HiveBinHeader>>,
     root_cell_offset: Option<Offset>,
     sequence_number: u32,
+    name_comparison: NameComparison,
+    max_subkeys_list_depth: usize,
+    codepage: Codepage,
+    mount_point: Option<String>,
     status: PhantomData<S>,
 }
 
@@ -84,47 +104,20 @@ where
 {
     /// creates a new [Hive] object. This includes parsing the HiveBaseBlock and determining
     /// the start of the hive bins data.
-    pub fn new(mut data: B, parse_mode: HiveParseMode) -> BinResult<Self> {
+    pub fn new(mut data: B, parse_mode: HiveParseMode) -> Result<Self> {
         data.seek(SeekFrom::Start(0))?;
         let mut data = MemOverlay::from(data);
         let me = match parse_mode {
-            HiveParseMode::Raw => Self {
-                data,
-                base_block: None,
-                root_cell_offset: None,
-                sequence_number: 0,
-                status: PhantomData,
-            },
-            HiveParseMode::Normal(offset) => Self {
-                data,
-                base_block: None,
-                root_cell_offset: Some(offset),
-                sequence_number: 0,
-                status: PhantomData,
-            },
+            HiveParseMode::Raw => Self::without_base_block(data, None),
+            HiveParseMode::Normal(offset) => Self::without_base_block(data, Some(offset)),
             HiveParseMode::NormalWithBaseBlock => {
-                /* preread the baseblock data to prevent seeking */
-                let mut baseblock_data = [0; BASEBLOCK_SIZE];
-                data.read_exact(&mut baseblock_data)?;
-
-                /* read baseblock */
-                let mut baseblock_cursor = Cursor::new(baseblock_data);
-                let base_block: HiveBaseBlock = baseblock_cursor
-                    .read_le_args((FileType::HiveFile,))
-                    .unwrap();
-                let data_offset = data.stream_position()? as usize;
-                if data_offset != BASEBLOCK_SIZE {
-                    panic!("we assume a base block size of {BASEBLOCK_SIZE} bytes, but the current has a size of {data_offset} bytes");
-                }
-
-                let root_cell_offset = *base_block.root_cell_offset();
-                let sequence_number = *base_block.primary_sequence_number();
-                Self {
-                    data,
-                    base_block: Some(base_block),
-                    root_cell_offset: Some(root_cell_offset),
-                    sequence_number,
-                    status: PhantomData,
+                let base_block = Self::read_base_block(&mut data)?;
+                Self::with_base_block(data, base_block)
+            }
+            HiveParseMode::NormalWithBaseBlockRecovery(backup_base_block) => {
+                match Self::read_base_block(&mut data) {
+                    Ok(base_block) => Self::with_base_block(data, base_block),
+                    Err(why) => Self::recover(data, why, backup_base_block)?,
                 }
             }
         };
@@ -132,20 +125,218 @@ where
         Ok(me)
     }
 
+    fn without_base_block(data: MemOverlay<B>, root_cell_offset: Option<Offset>) -> Self {
+        Self {
+            data,
+            base_block: None,
+            base_block_error: None,
+            first_hbin_timestamp: None,
+            hivebin_table: None,
+            root_cell_offset,
+            sequence_number: 0,
+            name_comparison: NameComparison::default(),
+            max_subkeys_list_depth: DEFAULT_MAX_SUBKEYS_LIST_DEPTH,
+            codepage: Codepage::default(),
+            mount_point: None,
+            status: PhantomData,
+        }
+    }
+
+    fn with_base_block(data: MemOverlay<B>, base_block: HiveBaseBlock) -> Self {
+        let root_cell_offset = *base_block.root_cell_offset();
+        let sequence_number = *base_block.primary_sequence_number();
+        Self {
+            data,
+            base_block: Some(base_block),
+            base_block_error: None,
+            first_hbin_timestamp: None,
+            hivebin_table: None,
+            root_cell_offset: Some(root_cell_offset),
+            sequence_number,
+            name_comparison: NameComparison::default(),
+            max_subkeys_list_depth: DEFAULT_MAX_SUBKEYS_LIST_DEPTH,
+            codepage: Codepage::default(),
+            mount_point: None,
+            status: PhantomData,
+        }
+    }
+
+    fn read_base_block(data: &mut MemOverlay<B>) -> Result<HiveBaseBlock> {
+        /* preread the baseblock data to prevent seeking */
+        let mut baseblock_data = [0; BASEBLOCK_SIZE];
+        data.read_exact(&mut baseblock_data)?;
+
+        let data_offset = data.stream_position()? as usize;
+        if data_offset != BASEBLOCK_SIZE {
+            return Err(Error::Io(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("we assume a base block size of {BASEBLOCK_SIZE} bytes, but the current has a size of {data_offset} bytes"),
+            )));
+        }
+
+        /* read baseblock */
+        let mut baseblock_cursor = Cursor::new(baseblock_data);
+        Ok(baseblock_cursor.read_le_args((FileType::HiveFile,))?)
+    }
+
+    /// is called if the base block cannot be read. If there is a backup of the base block,
+    /// this backup will be used. Otherwise, we use the timestamp of the first hive bin and
+    /// search for the root cell.
+    fn recover(
+        data: MemOverlay<B>,
+        error: Error,
+        backup_base_block: Option<Box<HiveBaseBlock>>,
+    ) -> Result<Self> {
+        log::warn!("unable to read the base block, trying to recover: {error}");
+
+        if let Some(base_block) = backup_base_block {
+            log::info!("using the backup copy of the base block");
+            let mut me = Self::with_base_block(data, *base_block);
+            me.base_block_error = Some(error.to_string());
+            return Ok(me);
+        }
+
+        let mut hive: Hive<B, CleanHive> = Hive::without_base_block(data, None);
+        hive.base_block_error = Some(error.to_string());
+
+        hive.seek(SeekFrom::Start(0))?;
+        hive.first_hbin_timestamp = match hive.read_le::<HiveBinHeader>() {
+            Ok(hivebin) if *hivebin.timestamp() != 0 => Some(*hivebin.timestamp()),
+            Ok(_) => None,
+            Err(why) => {
+                log::warn!("unable to read the first hive bin: {why}");
+                None
+            }
+        };
+
+        let hive = Rc::new(RefCell::new(hive));
+        let root_cell_offset = Hive::find_root_cell(Rc::clone(&hive));
+        // all other references to the hive have been dropped by find_root_cell()
+        let mut hive = match Rc::try_unwrap(hive) {
+            Ok(hive) => hive.into_inner(),
+            Err(_) => return Err(error),
+        };
+
+        match root_cell_offset {
+            Some(offset) => {
+                log::info!("found root cell at offset 0x{:08x}", offset.0);
+                hive.root_cell_offset = Some(offset);
+                Ok(hive.with_status())
+            }
+            None => {
+                log::warn!("unable to find the root cell");
+                Err(error)
+            }
+        }
+    }
+
+    fn with_status<T: HiveStatus>(self) -> Hive<B, T> {
+        Hive::<B, T> {
+            data: self.data,
+            base_block: self.base_block,
+            base_block_error: self.base_block_error,
+            first_hbin_timestamp: self.first_hbin_timestamp,
+
+            // the hive data might change, e.g. if transaction logs are applied
+            hivebin_table: None,
+            root_cell_offset: self.root_cell_offset,
+            sequence_number: self.sequence_number,
+            name_comparison: self.name_comparison,
+            max_subkeys_list_depth: self.max_subkeys_list_depth,
+            codepage: self.codepage,
+            mount_point: self.mount_point,
+            status: PhantomData,
+        }
+    }
+
+    /// returns a description of the error which occurred while reading the base block,
+    /// if the hive has been opened using [HiveParseMode::NormalWithBaseBlockRecovery] and
+    /// the base block could not be read
+    pub fn base_block_error(&self) -> Option<&str> {
+        self.base_block_error.as_deref()
+    }
+
+    /// returns the time when this hive has been written last. This is taken from
+    /// the base block, or from the first hive bin if the base block could not be read
+    pub fn last_written_timestamp(&self) -> Option<DateTime<Utc>> {
+        let raw_timestamp = match &self.base_block {
+            Some(base_block) => *base_block.timestamp(),
+            None => self.first_hbin_timestamp?,
+        };
+        Some(filetime_to_datetime(&raw_timestamp.to_le_bytes()))
+    }
+
     /// write the baseblock to some writer
     ///
     /// This method ignores any patches to the base block which might
     /// be introduced by log files, because the `apply_transaction_log()` method
     /// takes care of the base block and handles all necessary changes
-    pub fn write_baseblock<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+    pub fn write_baseblock<W: Write>(&self, writer: &mut W) -> Result<()> {
         match self.base_block() {
-            Some(base_block) => base_block.write(writer).map_err(|why| anyhow!(why)),
-            None => {
-                bail!("this hive has no base block");
-            }
+            Some(base_block) => Ok(base_block.write(writer)?),
+            None => Err(Error::MissingBaseBlock),
         }
     }
 
+    /// returns the information which is required to parse structures stored in this hive
+    pub fn parse_context(&self) -> ParseContext {
+        let ctx = match &self.base_block {
+            None => ParseContext::default(),
+            Some(base_block) => ParseContext::new(*base_block.minor_version()),
+        };
+        ctx.with_codepage(self.codepage)
+    }
+
+    /// returns the codepage which is used to decode names stored as (extended) ASCII
+    pub fn codepage(&self) -> Codepage {
+        self.codepage
+    }
+
+    /// specifies the codepage which is used to decode names stored as (extended) ASCII.
+    /// The default is [`Codepage::Latin1`]. This affects only structures which are
+    /// read after this call.
+    pub fn set_codepage(&mut self, codepage: Codepage) {
+        self.codepage = codepage;
+    }
+
+    /// returns the way how key names are compared
+    pub fn name_comparison(&self) -> NameComparison {
+        self.name_comparison
+    }
+
+    /// specifies how key names are compared, e.g. when searching for a subkey.
+    /// The default is [`NameComparison::NtUpcase`]
+    pub fn set_name_comparison(&mut self, name_comparison: NameComparison) {
+        self.name_comparison = name_comparison;
+    }
+
+    /// returns the maximum number of nested index roots which are followed
+    /// when the subkeys of a key are read
+    pub fn max_subkeys_list_depth(&self) -> usize {
+        self.max_subkeys_list_depth
+    }
+
+    /// specifies the maximum number of nested index roots which are followed when
+    /// the subkeys of a key are read. Windows itself only creates index roots which
+    /// refer to leafs, which is a depth of `1`. The default is
+    /// [`DEFAULT_MAX_SUBKEYS_LIST_DEPTH`].
+    pub fn set_max_subkeys_list_depth(&mut self, max_subkeys_list_depth: usize) {
+        self.max_subkeys_list_depth = max_subkeys_list_depth;
+    }
+
+    /// returns the path where this hive is mounted in the registry, such as `\REGISTRY\MACHINE\SYSTEM`
+    pub fn mount_point(&self) -> Option<&str> {
+        self.mount_point.as_deref()
+    }
+
+    /// specifies the path where this hive is mounted in the registry, such as
+    /// `\REGISTRY\MACHINE\SYSTEM`. Symbolic links can only be followed if their
+    /// target is below this path, because all other targets point into other hives.
+    /// By default, no mount point is known.
+    pub fn set_mount_point(&mut self, mount_point: impl Into<String>) {
+        self.mount_point = Some(mount_point.into());
+    }
+
     pub fn is_checksum_valid(&self) -> Option<bool> {
         if self.base_block().is_some() {
             let mut buffer = Cursor::new([0; BASEBLOCK_SIZE]);
@@ -153,7 +344,7 @@ where
             if self.write_baseblock(&mut buffer).is_err() {
                 return Some(false);
             }
-            buffer.seek(SeekFrom::Start(0)).unwrap();
+            buffer.set_position(0);
 
             match buffer.read_le_args::<HiveBaseBlock>((FileType::HiveFile,)) {
                 Ok(_) => Some(true),
@@ -173,19 +364,16 @@ where
     B: BinReaderExt,
 {
     pub fn treat_hive_as_clean(self) -> Hive<B, CleanHive> {
-        Hive::<B, CleanHive> {
-            data: self.data,
-            base_block: self.base_block,
-            root_cell_offset: self.root_cell_offset,
-            sequence_number: self.sequence_number,
-            status: PhantomData,
-        }
+        self.with_status()
     }
 
-    pub fn apply_transaction_log(&mut self, log: TransactionLogsEntry) -> ApplicationResult {
-        let base_block = self.base_block.as_ref().unwrap();
-        if *base_block.secondary_sequence_number() != 0
-            && *log.sequence_number() != base_block.secondary_sequence_number() + 1
+    /// applies all dirty pages of `log` to the hive data. Returns an error if the hive has
+    /// no base block, or if a dirty page cannot be placed in the hive data.
+    pub fn apply_transaction_log(&mut self, log: TransactionLogsEntry) -> Result<ApplicationResult> {
+        let base_block = self.base_block.as_ref().ok_or(Error::MissingBaseBlock)?;
+        if (*base_block.secondary_sequence_number() != 0
+            && *log.sequence_number() != base_block.secondary_sequence_number().wrapping_add(1))
+            || *log.sequence_number() < *base_block.primary_sequence_number()
         {
             log::warn!(
                 "abort applying transaction logs at sequence number {}",
@@ -195,7 +383,7 @@ where
                 "next log entry had transaction number: {}",
                 log.sequence_number()
             );
-            return ApplicationResult::SequenceNumberDoesNotMatch;
+            return Ok(ApplicationResult::SequenceNumberDoesNotMatch);
         }
         log::info!(
             "applying entry with sequence number {}",
@@ -203,24 +391,25 @@ where
         );
 
         for (reference, page) in log.dirty_pages_references().iter().zip(log.dirty_pages()) {
+            let offset = BASEBLOCK_SIZE as u64 + u64::from(reference.offset().0);
             log::info!(
                 "placing patch of size {} at 0x{:08x}",
                 page.len(),
-                BASEBLOCK_SIZE as u32 + reference.offset().0
+                offset
             );
 
-            if let Err(why) = self
-                .data
-                .add_bytes_at((BASEBLOCK_SIZE as u32 + reference.offset().0).into(), page)
-            {
-                panic!("unable to apply memory patch: {why}");
+            if let Err(why) = self.data.add_bytes_at(offset, page) {
+                return Err(Error::PatchFailed {
+                    offset,
+                    message: why.to_string(),
+                });
             }
         }
 
         if let Some(ref mut base_block) = self.base_block {
-            base_block.set_sequence_number(*log.sequence_number());
+            base_block.set_sequence_number(*log.sequence_number())?;
         }
-        ApplicationResult::Applied
+        Ok(ApplicationResult::Applied)
     }
 }
 
@@ -239,21 +428,36 @@ where
     /// Is this really needed???
     pub fn enum_subkeys(
         &mut self,
-        callback: fn(&mut Self, &KeyNode) -> BinResult<()>,
-    ) -> BinResult<()> {
+        callback: fn(&mut Self, &KeyNode) -> Result<()>,
+    ) -> Result<()> {
         let root_key_node = self.root_key_node()?;
         callback(self, &root_key_node)?;
         Ok(())
     }
 
     /// returns the root key of this registry hive file
-    pub fn root_key_node(&mut self) -> BinResult<KeyNode> {
-        let mkn: KeyNodeWithMagic = self.read_structure(self.root_cell_offset())?;
-        Ok(mkn.into())
+    pub fn root_key_node(&mut self) -> Result<KeyNode> {
+        self.key_at(self.root_cell_offset()?)
+    }
+
+    /// reads the key node which is stored in the cell at `offset`
+    pub fn key_at(&mut self, offset: Offset) -> Result<KeyNode> {
+        let mkn: KeyNodeWithMagic = self.read_structure(offset)?;
+        Ok(KeyNode::from(mkn).with_offset(offset))
+    }
+
+    /// reads the key value which is stored in the cell at `offset`
+    pub fn value_at(&mut self, offset: Offset) -> Result<KeyValue> {
+        let kvm: KeyValueWithMagic = self.read_structure(offset)?;
+        Ok(KeyValue::from(kvm).with_offset(offset))
     }
 
     /// reads a data structure from the given offset. Read the documentation of [Cell]
-    /// for a detailled discussion
+    /// for a detailled discussion. Structures which require a [ParseContext]
+    /// receive the context of this hive.
+    ///
+    /// Returns [`Error::DeletedCell`] if the cell is not allocated, and
+    /// [`Error::OffsetOutOfBounds`] if the cell exceeds the hive bins data.
     ///
     /// # Usage
     ///
@@ -265,25 +469,40 @@ where
     /// # fn main() -> Result<(), Box<dyn Error>> {
     /// # let hive_file = File::open("tests/data/testhive")?;
     /// # let mut hive = Hive::new(hive_file, HiveParseMode::NormalWithBaseBlock)?;
-    /// # let offset = hive.root_cell_offset();
+    /// # let offset = hive.root_cell_offset()?;
     /// let my_node: KeyNodeWithMagic = hive.read_structure(offset)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn read_structure<T>(&mut self, offset: Offset) -> BinResult<T>
+    pub fn read_structure<T>(&mut self, offset: Offset) -> Result<T>
     where
-        T: BinRead<Args = ()> + std::convert::From<crate::Cell<T, ()>>,
+        T: BinRead + std::convert::From<crate::Cell<T, T::Args>>,
+        T::Args: From<ParseContext>,
     {
         log::trace!(
             "reading cell of type {} from offset {:08x} (was: {:08x})",
             std::any::type_name::<T>(),
-            offset.0 + BASEBLOCK_SIZE as u32,
+            u64::from(offset.0) + BASEBLOCK_SIZE as u64,
             offset.0
         );
 
-        self.seek(SeekFrom::Start(offset.0.into()))?;
-        let cell: Cell<T, ()> = self.read_le().unwrap();
-        assert!(cell.is_allocated());
+        let args = T::Args::from(self.parse_context());
+        match self.seek(SeekFrom::Start(offset.0.into())) {
+            Err(why) if why.kind() == ErrorKind::UnexpectedEof => {
+                return Err(Error::OffsetOutOfBounds { offset })
+            }
+            result => result?,
+        };
+        let cell: Cell<T, T::Args> = match self.read_le_args(args) {
+            Ok(cell) => cell,
+            Err(binread::Error::Io(why)) if why.kind() == ErrorKind::UnexpectedEof => {
+                return Err(Error::OffsetOutOfBounds { offset })
+            }
+            Err(why) => return Err(why.into()),
+        };
+        if !cell.is_allocated() {
+            return Err(Error::DeletedCell { offset });
+        }
         Ok(cell.into())
     }
 
@@ -292,17 +511,26 @@ where
         BASEBLOCK_SIZE as u32
     }
 
-    /// returns the offset of the root cell
-    pub fn root_cell_offset(&self) -> Offset {
+    /// returns the offset of the root cell, or [`Error::MissingRootCell`] if
+    /// the offset is unknown
+    pub fn root_cell_offset(&self) -> Result<Offset> {
         match &self.base_block {
-            None => self.root_cell_offset.unwrap(),
-            Some(base_block) => *base_block.root_cell_offset(),
+            None => self.root_cell_offset.ok_or(Error::MissingRootCell),
+            Some(base_block) => Ok(*base_block.root_cell_offset()),
         }
     }
 
     pub fn find_root_celloffset(self) -> Option<Offset> {
-        for cell in self
-            .hivebins()
+        Self::find_root_cell(Rc::new(RefCell::new(self)))
+    }
+
+    /// searches for the root cell. This is the key node which has the flag
+    /// [`KEY_HIVE_ENTRY`](KeyNodeFlags::KEY_HIVE_ENTRY) set. Some hives don't set this flag,
+    /// so if there is no such key node we use the first key node whose parent is no key node.
+    fn find_root_cell(hive: Rc<RefCell<Self>>) -> Option<Offset> {
+        let mut key_nodes = HashSet::new();
+        let mut candidates = Vec::new();
+        for cell in HiveBinIterator::from(hive)
             .flat_map(|hb| hb.cells())
             .filter(|selector| !selector.header().is_deleted())
         {
@@ -310,14 +538,18 @@ where
                 if nk.flags.contains(KeyNodeFlags::KEY_HIVE_ENTRY) {
                     return Some(*cell.offset());
                 }
+                key_nodes.insert(cell.offset().0);
+                candidates.push((*cell.offset(), nk.parent));
             }
         }
-        None
+        candidates
+            .into_iter()
+            .find(|(_, parent)| !key_nodes.contains(&parent.0))
+            .map(|(offset, _)| offset)
     }
 
-    pub fn reset_cursor(&mut self) -> io::Result<()> {
-        self.data
-            .seek(SeekFrom::Start(BASEBLOCK_SIZE.try_into().unwrap()))?;
+    pub fn reset_cursor(&mut self) -> Result<()> {
+        self.data.seek(SeekFrom::Start(BASEBLOCK_SIZE as u64))?;
         Ok(())
     }
 
@@ -325,10 +557,66 @@ where
         HiveBinIterator::from(self)
     }
 
-    pub fn data_size(&self) -> u32 {
+    /// returns the headers of all hive bins, ordered by their offset. In contrast to
+    /// [hivebins](Self::hivebins), this does not consume the hive. The hive bins are
+    /// scanned only once, subsequent calls return the cached result.
+    pub fn hivebin_headers(&mut self) -> Result<&[HiveBinHeader]> {
+        let table = match self.hivebin_table.take() {
+            Some(table) => table,
+            None => self.scan_hivebin_headers()?,
+        };
+        Ok(self.hivebin_table.insert(table))
+    }
+
+    /// returns the header of the hive bin which contains `offset`, or [`None`]
+    /// if there is no such hive bin
+    pub fn hbin_containing(&mut self, offset: Offset) -> Result<Option<&HiveBinHeader>> {
+        let table = self.hivebin_headers()?;
+        let idx = table.partition_point(|hivebin| hivebin.offset().0 <= offset.0);
+        Ok(idx
+            .checked_sub(1)
+            .map(|idx| &table[idx])
+            .filter(|hivebin| hivebin.contains(offset)))
+    }
+
+    fn scan_hivebin_headers(&mut self) -> Result<Vec<HiveBinHeader>> {
+        let end_of_file = self.seek(SeekFrom::End(0))?;
+        let mut table = Vec::new();
+        let mut current_start = 0;
+
+        while current_start < end_of_file {
+            self.seek(SeekFrom::Start(current_start))?;
+            match self.read_le::<HiveBinHeader>() {
+                Ok(hivebin) => {
+                    if u64::from(hivebin.offset().0) != current_start {
+                        log::warn!(
+                            "hivebin at 0x{current_start:08x} claims to be at 0x{:08x}",
+                            hivebin.offset().0
+                        );
+                    }
+                    current_start += u64::from(*hivebin.size());
+                    table.push(hivebin);
+                }
+                Err(why) => {
+                    if let binread::Error::Io(kind) = &why {
+                        if kind.kind() == ErrorKind::UnexpectedEof {
+                            break;
+                        }
+                    }
+                    log::warn!("scanner error: {}", why);
+                    current_start += 0x1000;
+                }
+            }
+        }
+
+        table.sort_by_key(|hivebin| hivebin.offset().0);
+        Ok(table)
+    }
+
+    pub fn data_size(&self) -> Result<u32> {
         match &self.base_block {
-            None => panic!("this hive file has no base block"),
-            Some(base_block) => *base_block.data_size() - BASEBLOCK_SIZE as u32,
+            None => Err(Error::MissingBaseBlock),
+            Some(base_block) => Ok(base_block.data_size().saturating_sub(BASEBLOCK_SIZE as u32)),
         }
     }
 }
@@ -0,0 +1,86 @@
+use super::{Codepage, MAX_MINOR_VERSION};
+
+/// contains information about a hive which is required to correctly parse
+/// some of the structures stored in it. This is passed as argument to
+/// [`KeyNodeWithMagic`](crate::KeyNodeWithMagic) and
+/// [`KeyValueWithMagic`](crate::KeyValueWithMagic).
+///
+/// Use [`Hive::parse_context`](crate::Hive::parse_context) to obtain the context of some hive.
+///
+/// Structures which have been introduced after the format version of a hive are not
+/// treated as errors: fast leafs and hash leafs are read anyway (and reported by
+/// [`KeyNode::verify_subkeys_lists`](crate::KeyNode::verify_subkeys_lists)), while
+/// big data records are not interpreted before version 1.4, as Windows does. The data
+/// of such values is read from a single cell and the value is marked as invalid if
+/// this cell is too small.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ParseContext {
+    minor_version: u32,
+    codepage: Codepage,
+}
+
+impl ParseContext {
+    pub fn new(minor_version: u32) -> Self {
+        Self {
+            minor_version,
+            codepage: Codepage::default(),
+        }
+    }
+
+    /// Returns a copy of this context, which uses `codepage` to decode names
+    pub fn with_codepage(mut self, codepage: Codepage) -> Self {
+        self.codepage = codepage;
+        self
+    }
+
+    /// Returns the codepage which is used to decode names stored as (extended) ASCII
+    pub fn codepage(&self) -> Codepage {
+        self.codepage
+    }
+
+    /// Returns the minor version of the hive format
+    pub fn minor_version(&self) -> u32 {
+        self.minor_version
+    }
+
+    /// Returns [true] if the hive format supports fast leaf subkeys lists (`lf`),
+    /// which have been introduced with version 1.3. Older versions only use
+    /// index leaf (`li`) and index root (`ri`) lists.
+    pub fn supports_fast_leaf(&self) -> bool {
+        self.minor_version >= 3
+    }
+
+    /// Returns [true] if the hive format supports big data records (`db`),
+    /// which have been introduced with version 1.4
+    pub fn supports_big_data(&self) -> bool {
+        self.minor_version >= 4
+    }
+
+    /// Returns [true] if the hive format supports hash leaf subkeys lists (`lh`),
+    /// which have been introduced with version 1.5
+    pub fn supports_hash_leaf(&self) -> bool {
+        self.minor_version >= 5
+    }
+}
+
+/// If there is no base block, we cannot know the hive version,
+/// so we assume the newest one.
+impl Default for ParseContext {
+    fn default() -> Self {
+        Self::new(MAX_MINOR_VERSION)
+    }
+}
+
+/// allows [`Hive::read_structure`](crate::Hive::read_structure) to read structures which
+/// don't need a [`ParseContext`]
+impl From<ParseContext> for () {
+    fn from(_: ParseContext) -> Self {}
+}
+
+/// allows [`Hive::read_structure`](crate::Hive::read_structure) to read structures which
+/// need a [`ParseContext`]
+impl From<ParseContext> for (ParseContext,) {
+    fn from(ctx: ParseContext) -> Self {
+        (ctx,)
+    }
+}
@@ -3,7 +3,7 @@ use std::fmt::Debug;
 use std::io::{ErrorKind, Seek};
 use std::rc::Rc;
 
-use binread::{derive_binread, BinRead, BinReaderExt, BinResult};
+use binread::{derive_binread, BinRead, BinReaderExt};
 use derive_getters::Getters;
 use thiserror::Error;
 
@@ -27,14 +27,14 @@ where
     pub fn new(hivebin: &HiveBin<B>, hive: Rc<RefCell<Hive<B, CleanHive>>>) -> Self {
         Self {
             hive,
-            hivebin_size: (*hivebin.size()).try_into().unwrap(),
+            hivebin_size: *hivebin.size() as usize,
 
             // we assume that we already consumed the header
             consumed_bytes: hivebin.header_size().into(),
         }
     }
 
-    fn next_cell(&mut self) -> BinResult<Option<CellSelector>> {
+    fn next_cell(&mut self) -> Result<Option<CellSelector>> {
         const CELL_HEADER_SIZE: usize = 4;
 
         // if there is not enough space in this hivebin, give up
@@ -42,21 +42,32 @@ where
             return Ok(None);
         }
 
-        let cell_offset = self.hive.borrow_mut().stream_position().unwrap();
+        let cell_offset = self.hive.borrow_mut().stream_position()?;
+        let Ok(offset) = u32::try_from(cell_offset) else {
+            return Ok(None);
+        };
+        let offset = Offset(offset);
 
         let header: CellHeader = self.hive.borrow_mut().read_le()?;
 
         let cell_size = header.size();
-        let content: CellContent = self.hive.borrow_mut().read_le()?;
+        let ctx = self.hive.borrow().parse_context();
+        let content: CellContent = self.hive.borrow_mut().read_le_args((ctx,))?;
         self.consumed_bytes += cell_size;
 
+        let content = match content {
+            CellContent::NK(nk) => CellContent::NK(nk.with_offset(offset)),
+            CellContent::VK(vk) => CellContent::VK(vk.with_offset(offset)),
+            content => content,
+        };
+
         let cell_selector = CellSelector {
-            offset: Offset(cell_offset.try_into().unwrap()),
+            offset,
             header,
             content,
         };
         self.hive.borrow_mut().seek(std::io::SeekFrom::Start(
-            cell_offset + u64::try_from(cell_size).unwrap(),
+            cell_offset + cell_size as u64,
         ))?;
 
         Ok(Some(cell_selector))
@@ -73,7 +84,7 @@ where
         match self.next_cell() {
             Ok(v) => v,
             Err(why) => {
-                if let binread::Error::Io(kind) = &why {
+                if let Error::Io(kind) = &why {
                     if kind.kind() != ErrorKind::UnexpectedEof {
                         log::warn!("parser error: {}", why);
                     }
@@ -88,21 +99,24 @@ where
 
 #[derive(BinRead, Getters)]
 #[getter(get = "pub")]
+#[br(import(ctx: ParseContext))]
 pub struct CellSelector {
     offset: Offset,
     header: CellHeader,
+    #[br(args(ctx))]
     content: CellContent,
 }
 
 #[derive_binread]
 #[derive(Debug)]
+#[br(import(ctx: ParseContext))]
 pub enum CellContent {
     #[br(magic = b"nk")]
-    NK(KeyNode),
+    NK(#[br(args(ctx))] KeyNode),
     #[br(magic = b"vk")]
-    VK(KeyValue),
+    VK(#[br(args(ctx))] KeyValue),
     #[br(magic = b"sk")]
-    SK,
+    SK(KeySecurity),
     #[br(magic = b"db")]
     DB,
 
@@ -3,54 +3,74 @@ mod cell_iterator;
 use std::{cell::RefCell, ops::Deref, rc::Rc};
 pub use cell_iterator::*;
 
-use binread::{derive_binread, BinReaderExt, BinResult};
+use binread::{derive_binread, BinReaderExt};
+use chrono::{DateTime, Utc};
 use getset::Getters;
 
-use crate::{CleanHive, Hive, Offset};
+use crate::util::filetime_to_datetime;
+use crate::{CleanHive, Hive, Offset, Result};
 
+/// Represents the header of a hive bin, as documented in <https://github.com/msuhanov/regf/blob/master/Windows%20registry%20file%20format%20specification.md#hive-bin>
 #[derive_binread]
-#[derive(Getters)]
+#[derive(Getters, Debug, Clone)]
 #[br(magic = b"hbin")]
 #[allow(dead_code)]
 #[getset(get = "pub")]
-pub struct _HiveBin {
-    // Offset of a current hive bin in bytes, relative from the start of the
-    // hive bins data
+pub struct HiveBinHeader {
+    /// Offset of a current hive bin in bytes, relative from the start of the
+    /// hive bins data
     offset: Offset,
 
-    // Size of a current hive bin in bytes
-    #[br(assert(size & 0xfff == 0, "hivebins must be alligned at 4k boundaries"))]
+    /// Size of a current hive bin in bytes
+    #[br(assert(size & 0xfff == 0, "hivebins must be alligned at 4k boundaries"),
+        assert(size != 0, "hivebins must not be empty"))]
     size: u32,
 
     reserved: u64,
 
-    // FILETIME (UTC), defined for the first hive bin only (see below)
-    //
-    // A Timestamp in the header of the first hive bin acts as a backup copy of
-    // a Last written timestamp in the base block.
+    /// FILETIME (UTC), defined for the first hive bin only (see below)
+    ///
+    /// A Timestamp in the header of the first hive bin acts as a backup copy of
+    /// a Last written timestamp in the base block.
     timestamp: u64,
 
-    // This field has no meaning on a disk (see below)
-    //
-    // The Spare field is used when shifting hive bins and cells in memory. In
-    // Windows 2000, the same field is called MemAlloc, it is used to track
-    // memory allocations for hive bins.
+    /// This field has no meaning on a disk (see below)
+    ///
+    /// The Spare field is used when shifting hive bins and cells in memory. In
+    /// Windows 2000, the same field is called MemAlloc, it is used to track
+    /// memory allocations for hive bins.
     spare: u32,
 }
 
+impl HiveBinHeader {
+    /// returns [true] if `offset` points into this hive bin
+    pub fn contains(&self, offset: Offset) -> bool {
+        offset.0 >= self.offset.0 && offset.0 - self.offset.0 < self.size
+    }
+
+    /// returns the timestamp of this hive bin, if there is one
+    pub fn timestamp_as_datetime(&self) -> Option<DateTime<Utc>> {
+        if self.timestamp == 0 {
+            None
+        } else {
+            Some(filetime_to_datetime(&self.timestamp.to_le_bytes()))
+        }
+    }
+}
+
 pub struct HiveBin<B>
 where
     B: BinReaderExt,
 {
     hive: Rc<RefCell<Hive<B, CleanHive>>>,
-    hivebin: _HiveBin,
+    hivebin: HiveBinHeader,
 }
 
 impl<B> HiveBin<B>
 where
     B: BinReaderExt,
 {
-    pub fn new(hive: Rc<RefCell<Hive<B, CleanHive>>>) -> BinResult<Self> {
+    pub fn new(hive: Rc<RefCell<Hive<B, CleanHive>>>) -> Result<Self> {
         let hivebin = hive.borrow_mut().read_le()?;
         Ok(Self { hive, hivebin })
     }
@@ -68,7 +88,7 @@ impl<B> Deref for HiveBin<B>
 where
     B: BinReaderExt,
 {
-    type Target = _HiveBin;
+    type Target = HiveBinHeader;
 
     fn deref(&self) -> &Self::Target {
         &self.hivebin
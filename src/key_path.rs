@@ -0,0 +1,73 @@
+use std::fmt::Display;
+
+use crate::Offset;
+
+/// Represents the full path of a key, as it is returned by
+/// [`KeyNode::path`](crate::KeyNode::path). The path starts with the name of the root key.
+///
+/// If the parent chain of a key is damaged, the path is incomplete. In this case, the
+/// path starts with the name of the topmost key which could be reached, and
+/// [termination](Self::termination) tells why no further parents could be read.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct KeyPath {
+    components: Vec<String>,
+    termination: PathTermination,
+}
+
+/// Describes why the reconstruction of a [KeyPath] stopped
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PathTermination {
+    /// the root key of the hive has been reached
+    Root,
+
+    /// the parent at the given offset has already been visited
+    Loop(Offset),
+
+    /// the parent at the given offset could not be read as a key node
+    UnreachableParent(Offset),
+}
+
+impl KeyPath {
+    /// `components` must be ordered from the root key to the key itself
+    pub(crate) fn new(components: Vec<String>, termination: PathTermination) -> Self {
+        Self {
+            components,
+            termination,
+        }
+    }
+
+    /// Returns the names of all keys in this path, starting with the topmost key
+    pub fn components(&self) -> &[String] {
+        &self.components
+    }
+
+    /// Returns the reason why the reconstruction of this path stopped
+    pub fn termination(&self) -> PathTermination {
+        self.termination
+    }
+
+    /// Returns [true] if the path reaches up to the root key
+    pub fn is_complete(&self) -> bool {
+        self.termination == PathTermination::Root
+    }
+
+    /// Returns the path relative to the root key, which can be passed to
+    /// [`SubPath::subpath`](crate::SubPath::subpath) of the root key.
+    /// Returns [`None`] if the path is incomplete.
+    pub fn relative_to_root(&self) -> Option<String> {
+        if self.is_complete() {
+            Some(self.components[1..].join("\\"))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for KeyPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.is_complete() {
+            write!(f, "...\\")?;
+        }
+        write!(f, "{}", self.components.join("\\"))
+    }
+}
@@ -0,0 +1,240 @@
+use std::cell::{Ref, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use binread::BinReaderExt;
+
+use crate::hive::CleanHive;
+use crate::nk::{KeyNode, LayerSemantics};
+use crate::vk::KeyValue;
+use crate::{Hive, NameComparison, Result};
+
+/// Represents a stack of registry hives, which consists of a base hive and an arbitrary number
+/// of layered hives (also known as differencing hives, version 1.6) on top of it. Such hives
+/// are used by Windows containers and by MSIX app layers.
+///
+/// [LayeredHive] provides the effective view of all layers, which is built by applying the
+/// [LayerSemantics] of every key and the tombstone flag of every value.
+///
+/// # Usage
+///
+/// ```
+/// # use std::error::Error;
+/// # use std::fs::File;
+/// use nt_hive2::*;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let base = Hive::new(File::open("tests/data/testhive")?, HiveParseMode::NormalWithBaseBlock)?;
+/// let layer = Hive::new(File::open("tests/data/testhive")?, HiveParseMode::NormalWithBaseBlock)?;
+/// let mut hive = LayeredHive::new(base);
+/// hive.push_layer(layer);
+///
+/// let key = hive.subpath("data-test")?.unwrap();
+/// for value in key.values() {
+///     println!("\"{}\" = {}", value.name(), value.value());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct LayeredHive<B>
+where
+    B: BinReaderExt,
+{
+    layers: Vec<Hive<B, CleanHive>>,
+}
+
+/// Represents a key of a [LayeredHive], which is built from the key nodes
+/// with the same path in all layers
+#[derive(Debug, Clone)]
+pub struct MergedKey {
+    name: String,
+
+    /// the way how value names are compared, see [`LayeredHive::name_comparison`]
+    name_comparison: NameComparison,
+
+    /// all key nodes which contribute to this key, ordered from the lowest to the highest layer
+    key_nodes: Vec<(usize, Rc<RefCell<KeyNode>>)>,
+}
+
+impl<B> LayeredHive<B>
+where
+    B: BinReaderExt,
+{
+    /// creates a new [LayeredHive], which consists only of the base hive
+    pub fn new(base: Hive<B, CleanHive>) -> Self {
+        Self { layers: vec![base] }
+    }
+
+    /// puts another layer on top of all existing layers
+    pub fn push_layer(&mut self, layer: Hive<B, CleanHive>) {
+        self.layers.push(layer);
+    }
+
+    /// returns the number of layers, including the base hive
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// returns the way how key names are compared. This is taken from the base hive,
+    /// see [`Hive::set_name_comparison`]
+    pub fn name_comparison(&self) -> NameComparison {
+        self.layers[0].name_comparison()
+    }
+
+    /// returns the root key of the merged view, or [`None`] if the root key has been deleted
+    pub fn root_key(&mut self) -> Result<Option<MergedKey>> {
+        let mut key_nodes = Vec::with_capacity(self.layers.len());
+        for (layer, hive) in self.layers.iter_mut().enumerate() {
+            key_nodes.push((layer, Rc::new(RefCell::new(hive.root_key_node()?))));
+        }
+        Ok(MergedKey::from_key_nodes(key_nodes, self.name_comparison()))
+    }
+
+    /// returns the subkeys of `key`, as they are visible in the merged view
+    pub fn subkeys(&mut self, key: &MergedKey) -> Result<Vec<MergedKey>> {
+        let mut children: Vec<Vec<(usize, Rc<RefCell<KeyNode>>)>> = Vec::new();
+        let mut index: HashMap<Vec<u16>, usize> = HashMap::new();
+        let comparison = self.name_comparison();
+
+        for (layer, key_node) in key.key_nodes.iter() {
+            let key_node = key_node.borrow();
+            for subkey in key_node.subkeys(&mut self.layers[*layer])?.iter() {
+                let name = comparison.normalize_utf16(&subkey.borrow().decoded_name_utf16());
+                let idx = *index.entry(name).or_insert_with(|| {
+                    children.push(Vec::new());
+                    children.len() - 1
+                });
+                children[idx].push((*layer, Rc::clone(subkey)));
+            }
+        }
+
+        Ok(children
+            .into_iter()
+            .filter_map(|key_nodes| MergedKey::from_key_nodes(key_nodes, comparison))
+            .collect())
+    }
+
+    /// returns the key with the given path (relative to the root key), if it is visible in the merged view
+    pub fn subpath(&mut self, path: &str) -> Result<Option<MergedKey>> {
+        let mut key = match self.root_key()? {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+
+        let comparison = self.name_comparison();
+        for part in path.split('\\').filter(|p| !p.is_empty()) {
+            match self
+                .subkeys(&key)?
+                .into_iter()
+                .find(|k| comparison.eq(&k.name, part))
+            {
+                Some(subkey) => key = subkey,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(key))
+    }
+
+    /// returns the class name of `key`. Keys in upper layers can inherit the class name
+    /// from lower layers.
+    pub fn class_name(&mut self, key: &MergedKey) -> Result<Option<String>> {
+        for (layer, key_node) in key.key_nodes.iter().rev() {
+            let key_node = key_node.borrow();
+            if !key_node.inherits_class() {
+                return key_node.class_name(&mut self.layers[*layer]);
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl MergedKey {
+    /// `key_nodes` must be ordered from the lowest to the highest layer
+    fn from_key_nodes(
+        key_nodes: Vec<(usize, Rc<RefCell<KeyNode>>)>,
+        name_comparison: NameComparison,
+    ) -> Option<Self> {
+        let semantics: Vec<_> = key_nodes
+            .iter()
+            .map(|(_, k)| k.borrow().layer_semantics())
+            .collect();
+        let lowest = lowest_visible_layer(&semantics)?;
+        let key_nodes: Vec<_> = key_nodes.into_iter().skip(lowest).collect();
+        let name = key_nodes.last()?.1.borrow().name().to_string();
+        Some(Self {
+            name,
+            name_comparison,
+            key_nodes,
+        })
+    }
+
+    /// Returns the name of this key, as it is stored in the highest layer
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns all key nodes which contribute to this key, together with the index
+    /// of their layer. The key nodes are ordered from the lowest to the highest layer.
+    pub fn key_nodes(&self) -> &Vec<(usize, Rc<RefCell<KeyNode>>)> {
+        &self.key_nodes
+    }
+
+    /// Returns the values of this key, as they are visible in the merged view. Values of
+    /// higher layers hide values with the same name of lower layers, and tombstone
+    /// values are not returned. Names are compared using [`LayeredHive::name_comparison`].
+    pub fn values(&self) -> Vec<Ref<'_, KeyValue>> {
+        let mut seen = HashSet::new();
+        let mut values = Vec::new();
+
+        for (_, key_node) in self.key_nodes.iter().rev() {
+            let borrowed = key_node.borrow();
+            for (idx, value) in borrowed.values().iter().enumerate() {
+                let name = self
+                    .name_comparison
+                    .normalize_utf16(&value.decoded_name_utf16());
+                if seen.insert(name) && !value.is_tombstone() {
+                    values.push(Ref::map(key_node.borrow(), |k| &k.values()[idx]));
+                }
+            }
+
+            if borrowed.layer_semantics() == LayerSemantics::SupersedeLocal {
+                break;
+            }
+        }
+        values
+    }
+}
+
+/// returns the index of the lowest layer which is visible, or [`None`]
+/// if the key has been deleted. `semantics` must be ordered from the
+/// lowest to the highest layer
+fn lowest_visible_layer(semantics: &[LayerSemantics]) -> Option<usize> {
+    let mut lowest = None;
+    for (idx, s) in semantics.iter().enumerate().rev() {
+        match s {
+            LayerSemantics::Tombstone => break,
+            LayerSemantics::SupersedeTree => {
+                lowest = Some(idx);
+                break;
+            }
+            LayerSemantics::None | LayerSemantics::SupersedeLocal => lowest = Some(idx),
+        }
+    }
+    lowest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LayerSemantics::*;
+
+    #[test]
+    fn test_lowest_visible_layer() {
+        assert_eq!(lowest_visible_layer(&[None, None]), Some(0));
+        assert_eq!(lowest_visible_layer(&[None, Tombstone]), Option::None);
+        assert_eq!(lowest_visible_layer(&[None, Tombstone, None]), Some(2));
+        assert_eq!(lowest_visible_layer(&[None, SupersedeTree, None]), Some(1));
+        assert_eq!(lowest_visible_layer(&[None, SupersedeLocal, None]), Some(0));
+        assert_eq!(lowest_visible_layer(&[Tombstone]), Option::None);
+    }
+}
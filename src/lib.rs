@@ -33,12 +33,35 @@ mod util;
 mod cell;
 mod nk;
 mod vk;
+mod sk;
+mod resource_list;
 mod db;
+mod data_provenance;
+mod layered_hive;
+mod key_path;
+mod name_comparison;
 mod subkeys_list;
 mod cell_with_u8_list;
+mod error;
 pub mod transactionlog;
 
 pub use cell::*;
-pub use hive::{Hive, Offset, HiveParseMode, ContainsHive, BaseBlock, CleanHive, DirtyHive, BASEBLOCK_SIZE, HiveWithLogs};
-pub use nk::{KeyNode, KeyNodeWithMagic, SubPath};
-pub use vk::{KeyValue, KeyValueWithMagic, RegistryValue};
\ No newline at end of file
+pub use error::{Error, Result};
+pub use hivebin::HiveBinHeader;
+pub use hive::{Hive, Offset, HiveParseMode, ContainsHive, BaseBlock, CleanHive, DirtyHive, BASEBLOCK_SIZE, DEFAULT_MAX_SUBKEYS_LIST_DEPTH, HiveWithLogs, ParseContext, UnsupportedVersion, HiveBaseBlock, BaseBlockFlags, ReorganizationType, Codepage};
+pub use nk::{KeyNode, KeyNodeFlags, KeyNodeWithMagic, LayerSemantics, SubPath, SymlinkMode, VirtualizationControlFlags};
+pub use layered_hive::{LayeredHive, MergedKey};
+pub use key_path::{KeyPath, PathTermination};
+pub use name_comparison::NameComparison;
+pub use subkeys_list::{SubkeysListError, SubkeysListIssue};
+pub use vk::{KeyValue, KeyValueDataType, KeyValueWithMagic, RegistryValue};
+pub use data_provenance::{DataCell, DataCellRole, DataProvenance, DataReadError};
+pub use resource_list::{
+    FullResourceDescriptor, InterfaceType, IoResource, IoResourceDescriptor, IoResourceList,
+    PartialResource, PartialResourceDescriptor, ResourceList, ResourceRequirementsList,
+    ResourceType, ShareDisposition,
+};
+pub use sk::{
+    Ace, AceData, AceFlags, AceType, Acl, KeyAccessRights, KeySecurity, KeySecurityWithMagic,
+    SecurityDescriptor, SecurityDescriptorControl, Sid,
+};
\ No newline at end of file
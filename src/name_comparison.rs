@@ -0,0 +1,287 @@
+/// Specifies how names of keys are compared. Key names are not case sensitive,
+/// but there are different ways to ignore the case of non-ASCII characters.
+///
+/// Use [`Hive::set_name_comparison`](crate::Hive::set_name_comparison) to select the
+/// comparison which is used by [`KeyNode::subkey`](crate::KeyNode::subkey) and
+/// [`SubPath::subpath`](crate::SubPath::subpath).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum NameComparison {
+    /// Compare names the way Windows does: every UTF-16 code unit is converted to upper
+    /// case on its own, and code units which have no single upper case counterpart are
+    /// kept as they are. This is the default.
+    #[default]
+    NtUpcase,
+
+    /// Compare the lower case representations of the names, as returned by
+    /// [`str::to_lowercase`]. This was the only available behaviour in earlier versions
+    /// of this crate.
+    Unicode,
+}
+
+impl NameComparison {
+    /// returns [true] if both names are considered as being equal
+    pub fn eq(&self, a: &str, b: &str) -> bool {
+        match self {
+            NameComparison::NtUpcase => a
+                .encode_utf16()
+                .map(nt_upcase)
+                .eq(b.encode_utf16().map(nt_upcase)),
+            NameComparison::Unicode => a.to_lowercase() == b.to_lowercase(),
+        }
+    }
+
+    /// returns [true] if both names (as UTF-16 code units) are considered as being equal.
+    /// In contrast to [`eq`](Self::eq), this can also compare names which are not valid UTF-16.
+    pub fn eq_utf16(&self, a: &[u16], b: &[u16]) -> bool {
+        match self {
+            NameComparison::NtUpcase => a
+                .iter()
+                .map(|c| nt_upcase(*c))
+                .eq(b.iter().map(|c| nt_upcase(*c))),
+            NameComparison::Unicode => {
+                String::from_utf16_lossy(a).to_lowercase()
+                    == String::from_utf16_lossy(b).to_lowercase()
+            }
+        }
+    }
+
+    /// returns a representation of `name` which is equal for all names which are
+    /// equal according to this comparison. This can be used as key of a map.
+    pub fn normalize(&self, name: &str) -> String {
+        match self {
+            NameComparison::NtUpcase => nt_upcase_str(name),
+            NameComparison::Unicode => name.to_lowercase(),
+        }
+    }
+
+    /// same as [`normalize`](Self::normalize), but works with names which are
+    /// not valid UTF-16
+    pub fn normalize_utf16(&self, name: &[u16]) -> Vec<u16> {
+        match self {
+            NameComparison::NtUpcase => name.iter().map(|c| nt_upcase(*c)).collect(),
+            NameComparison::Unicode => String::from_utf16_lossy(name)
+                .to_lowercase()
+                .encode_utf16()
+                .collect(),
+        }
+    }
+}
+
+/// converts a single UTF-16 code unit to upper case, like `RtlUpcaseUnicodeChar` does.
+/// This uses a fixed table instead of the Unicode data of the Rust standard library,
+/// because Windows does not follow newer Unicode versions.
+pub(crate) fn nt_upcase(c: u16) -> u16 {
+    UPCASE_TABLE[usize::from(c)]
+}
+
+/// the upper case table of Windows (since Windows Vista), which is the same table
+/// that is stored in the `$UpCase` file of NTFS volumes. Characters which are not
+/// contained in this table are not changed.
+///
+/// The ranges below are those which `ntfs_upcase_table_build()` in `libntfs-3g/unistr.c`
+/// of ntfs-3g (<https://github.com/tuxera/ntfs-3g>) uses to create the `$UpCase` table
+/// of Windows Vista and later.
+static UPCASE_TABLE: [u16; 0x10000] = build_upcase_table();
+
+/// ranges of characters (start inclusive, end exclusive) and the difference
+/// to their upper case counterparts
+#[rustfmt::skip]
+const UPCASE_RUNS: [(u16, u16, i32); 47] = [
+    (0x0061, 0x007b, -32), (0x00e0, 0x00f7, -32), (0x00f8, 0x00ff, -32),
+    (0x0256, 0x0258, -205), (0x028a, 0x028c, -217), (0x037b, 0x037e, 130),
+    (0x03ac, 0x03ad, -38), (0x03ad, 0x03b0, -37), (0x03b1, 0x03c2, -32),
+    (0x03c2, 0x03c3, -31), (0x03c3, 0x03cc, -32), (0x03cc, 0x03cd, -64),
+    (0x03cd, 0x03cf, -63), (0x0430, 0x0450, -32), (0x0450, 0x0460, -80),
+    (0x0561, 0x0587, -48), (0x1f00, 0x1f08, 8), (0x1f10, 0x1f16, 8),
+    (0x1f20, 0x1f28, 8), (0x1f30, 0x1f38, 8), (0x1f40, 0x1f46, 8),
+    (0x1f51, 0x1f52, 8), (0x1f53, 0x1f54, 8), (0x1f55, 0x1f56, 8),
+    (0x1f57, 0x1f58, 8), (0x1f60, 0x1f68, 8), (0x1f70, 0x1f72, 74),
+    (0x1f72, 0x1f76, 86), (0x1f76, 0x1f78, 100), (0x1f78, 0x1f7a, 128),
+    (0x1f7a, 0x1f7c, 112), (0x1f7c, 0x1f7e, 126), (0x1f80, 0x1f88, 8),
+    (0x1f90, 0x1f98, 8), (0x1fa0, 0x1fa8, 8), (0x1fb0, 0x1fb2, 8),
+    (0x1fb3, 0x1fb4, 9), (0x1fcc, 0x1fcd, -9), (0x1fd0, 0x1fd2, 8),
+    (0x1fe0, 0x1fe2, 8), (0x1fe5, 0x1fe6, 7), (0x1ffc, 0x1ffd, -9),
+    (0x2170, 0x2180, -16), (0x24d0, 0x24ea, -26), (0x2c30, 0x2c5f, -48),
+    (0x2d00, 0x2d26, -7264), (0xff41, 0xff5b, -32),
+];
+
+/// ranges of characters (start inclusive, end exclusive), where upper and lower case
+/// characters alternate. Every second character is the lower case form of its predecessor.
+#[rustfmt::skip]
+const UPCASE_PAIRS: [(u16, u16); 33] = [
+    (0x0100, 0x012f), (0x0132, 0x0137), (0x0139, 0x0149), (0x014a, 0x0178),
+    (0x0179, 0x017e), (0x01a0, 0x01a6), (0x01b3, 0x01b7), (0x01cd, 0x01dd),
+    (0x01de, 0x01ef), (0x01f4, 0x01f5), (0x01f8, 0x01f9), (0x01fa, 0x0220),
+    (0x0222, 0x0234), (0x023b, 0x023c), (0x0241, 0x0242), (0x0246, 0x024f),
+    (0x03d8, 0x03ef), (0x03f7, 0x03f8), (0x03fa, 0x03fb), (0x0460, 0x0481),
+    (0x048a, 0x04bf), (0x04c1, 0x04c4), (0x04c5, 0x04c8), (0x04c9, 0x04ce),
+    (0x04d0, 0x04f9), (0x0500, 0x050f), (0x1e00, 0x1e95), (0x1ea0, 0x1ef9),
+    (0x2183, 0x2184), (0x2c60, 0x2c61), (0x2c67, 0x2c6c), (0x2c75, 0x2c76),
+    (0x2c80, 0x2ce3),
+];
+
+/// characters whose upper case counterparts do not fit into [`UPCASE_RUNS`] or [`UPCASE_PAIRS`]
+#[rustfmt::skip]
+const UPCASE_SINGLES: [(u16, u16); 47] = [
+    (0x00ff, 0x0178), (0x0180, 0x0243), (0x0183, 0x0182), (0x0185, 0x0184),
+    (0x0188, 0x0187), (0x018c, 0x018b), (0x0192, 0x0191), (0x0195, 0x01f6),
+    (0x0199, 0x0198), (0x019a, 0x023d), (0x019e, 0x0220), (0x01a8, 0x01a7),
+    (0x01ad, 0x01ac), (0x01b0, 0x01af), (0x01b9, 0x01b8), (0x01bd, 0x01bc),
+    (0x01bf, 0x01f7), (0x01c6, 0x01c4), (0x01c9, 0x01c7), (0x01cc, 0x01ca),
+    (0x01dd, 0x018e), (0x01f3, 0x01f1), (0x2c65, 0x023a), (0x2c66, 0x023e),
+    (0x0253, 0x0181), (0x0254, 0x0186), (0x0259, 0x018f), (0x025b, 0x0190),
+    (0x0260, 0x0193), (0x0263, 0x0194), (0x0268, 0x0197), (0x0269, 0x0196),
+    (0x026b, 0x2c62), (0x026f, 0x019c), (0x0272, 0x019d), (0x0275, 0x019f),
+    (0x027d, 0x2c64), (0x0280, 0x01a6), (0x0283, 0x01a9), (0x0288, 0x01ae),
+    (0x0289, 0x0244), (0x028c, 0x0245), (0x0292, 0x01b7), (0x03f2, 0x03f9),
+    (0x04cf, 0x04c0), (0x1d7d, 0x2c63), (0x214e, 0x2132),
+];
+
+const fn build_upcase_table() -> [u16; 0x10000] {
+    let mut table = [0u16; 0x10000];
+    let mut i = 0;
+    while i < table.len() {
+        table[i] = i as u16;
+        i += 1;
+    }
+
+    let mut r = 0;
+    while r < UPCASE_RUNS.len() {
+        let (start, end, add) = UPCASE_RUNS[r];
+        let mut c = start;
+        while c < end {
+            table[c as usize] = (c as i32 + add) as u16;
+            c += 1;
+        }
+        r += 1;
+    }
+
+    let mut r = 0;
+    while r < UPCASE_PAIRS.len() {
+        let (start, end) = UPCASE_PAIRS[r];
+        let mut c = start;
+        while c < end {
+            table[c as usize + 1] = c;
+            c += 2;
+        }
+        r += 1;
+    }
+
+    let mut r = 0;
+    while r < UPCASE_SINGLES.len() {
+        let (lower, upper) = UPCASE_SINGLES[r];
+        table[lower as usize] = upper;
+        r += 1;
+    }
+    table
+}
+
+fn nt_upcase_str(name: &str) -> String {
+    let upcased: Vec<u16> = name.encode_utf16().map(nt_upcase).collect();
+    String::from_utf16_lossy(&upcased)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nt_upcase() {
+        assert_eq!(nt_upcase(u16::from(b'a')), u16::from(b'A'));
+        assert_eq!(nt_upcase(u16::from(b'1')), u16::from(b'1'));
+
+        // final sigma
+        assert_eq!(nt_upcase(0x03c2), 0x03a3);
+
+        // sharp s has no single upper case character
+        assert_eq!(nt_upcase(0x00df), 0x00df);
+
+        // surrogates are not changed
+        assert_eq!(nt_upcase(0xd83d), 0xd83d);
+
+        // characters which got an upper case form in newer Unicode versions
+        // are not changed, like Windows does
+        assert_eq!(nt_upcase(0x10d0), 0x10d0);
+        assert_eq!(nt_upcase(0x0250), 0x0250);
+
+        // characters which have been added with Unicode 5.0
+        assert_eq!(nt_upcase(0x2c65), 0x023a);
+        assert_eq!(nt_upcase(0x2c76), 0x2c75);
+        assert_eq!(nt_upcase(0x04cf), 0x04c0);
+
+        // Windows does not upcase these characters
+        assert_eq!(nt_upcase(0x00b5), 0x00b5);
+        assert_eq!(nt_upcase(0x0131), 0x0131);
+        assert_eq!(nt_upcase(0x01c5), 0x01c5);
+    }
+
+    #[test]
+    fn test_upcase_table() {
+        // every character which is changed has the same upper case form in Unicode, or is
+        // the lower case form of its upper case counterpart (such as the Greek letters with
+        // iota subscript, whose full upper case forms consist of two characters). Windows
+        // converts two of the upper case letters with iota subscript to lower case.
+        let to_lower_case = [0x1fcc, 0x1ffc];
+        let mut changed = 0;
+        for (c, upper) in UPCASE_TABLE.iter().enumerate() {
+            if usize::from(*upper) == c {
+                continue;
+            }
+            changed += 1;
+            if to_lower_case.contains(&c) {
+                assert_eq!(usize::from(*upper), c - 9);
+                continue;
+            }
+            let lower = char::from_u32(c as u32).unwrap();
+            let upper = char::from_u32(u32::from(*upper)).unwrap();
+            assert!(
+                lower.to_uppercase().eq([upper]) || upper.to_lowercase().eq([lower]),
+                "{lower:?} -> {upper:?}"
+            );
+        }
+        assert_eq!(changed, 869);
+
+        // FNV-1a hash of the whole table, which detects any modification of the ranges
+        let hash = UPCASE_TABLE
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325u64, |hash, c| {
+                (hash ^ u64::from(*c)).wrapping_mul(0x0000_0100_0000_01b3)
+            });
+        assert_eq!(hash, 0x2424_89c0_e080_316a);
+    }
+
+    #[test]
+    fn test_name_comparison() {
+        let nt = NameComparison::NtUpcase;
+        let unicode = NameComparison::Unicode;
+
+        assert!(nt.eq("Software", "SOFTWARE"));
+        assert!(unicode.eq("Software", "SOFTWARE"));
+
+        // final sigma and small sigma have the same upper case form,
+        // but different lower case forms
+        assert!(nt.eq("ΟΔΟΣ", "οδος"));
+        assert!(!unicode.eq("οδος", "οδοσ"));
+        assert!(nt.eq("οδος", "οδοσ"));
+
+        // the lower case form of the turkish dotted I consists of two characters,
+        // which Windows does not combine again
+        assert!(!nt.eq("İ", "i\u{307}"));
+        assert!(unicode.eq("İ", "i\u{307}"));
+        assert!(!nt.eq("İ", "i"));
+        assert!(!unicode.eq("İ", "I"));
+
+        // sharp s is kept as it is
+        assert!(!nt.eq("straße", "STRASSE"));
+        assert!(nt.eq("straße", "STRAßE"));
+
+        // Rust knows upper case forms of these characters, but Windows does not
+        assert!(!nt.eq("\u{10D0}", "\u{1C90}"));
+        assert!(!nt.eq("\u{0250}", "\u{2C6F}"));
+        assert!(unicode.eq("\u{10D0}", "\u{1C90}"));
+
+        // Georgian and Glagolitic letters which are known to Windows
+        assert!(nt.eq("\u{2D00}", "\u{10A0}"));
+        assert!(nt.eq("\u{2C30}", "\u{2C00}"));
+    }
+}
@@ -1,20 +1,31 @@
 use std::cell::Ref;
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
 use std::rc::Rc;
 
+use crate::cell_with_u8_list::CellWithU8List;
 use crate::hive::CleanHive;
+use crate::key_path::{KeyPath, PathTermination};
+use crate::name_comparison::nt_upcase;
+use crate::sk::{KeySecurity, KeySecurityWithMagic};
 use crate::subkeys_list::*;
-use crate::util::{parse_string, parse_timestamp};
+use crate::util::{parse_name, parse_reg_sz_raw, parse_timestamp, raw_name_to_utf16};
 use crate::vk::KeyValue;
 use crate::vk::KeyValueCell;
 use crate::vk::KeyValueList;
 use crate::vk::KeyValueWithMagic;
+use crate::vk::RegistryValue;
 use crate::Cell;
+use crate::Codepage;
+use crate::Error;
 use crate::Hive;
+use crate::NameComparison;
 use crate::Offset;
+use crate::ParseContext;
+use crate::Result;
 use binread::derive_binread;
 use binread::BinRead;
 use binread::BinReaderExt;
@@ -26,15 +37,24 @@ use chrono::DateTime;
 use chrono::Utc;
 
 #[derive(BinRead)]
-#[br(magic = b"nk")]
-pub struct KeyNodeWithMagic(KeyNode);
+#[br(magic = b"nk", import(ctx: ParseContext))]
+pub struct KeyNodeWithMagic(#[br(args(ctx))] KeyNode);
 
 /// represents a registry key node (as documented in <https://github.com/msuhanov/regf/blob/master/Windows%20registry%20file%20format%20specification.md#key-node>)
 #[allow(dead_code)]
 #[derive_binread]
 #[derive(Debug)]
+#[br(import(ctx: ParseContext))]
 pub struct KeyNode {
+    /// offset of the cell which contains this key node. This is not part of the
+    /// key node record itself and will be set after the key node has been read
+    #[br(default)]
+    offset: Offset,
+
     #[br(parse_with=parse_node_flags)]
+    raw_flags: u16,
+
+    #[br(calc(KeyNodeFlags::from_bits_truncate(raw_flags)))]
     pub(crate) flags: KeyNodeFlags,
 
     #[br(parse_with=parse_timestamp)]
@@ -43,14 +63,9 @@ pub struct KeyNode {
     pub parent: Offset,
     subkey_count: u32,
 
-    #[br(temp)]
     volatile_subkey_count: u32,
     subkeys_list_offset: Offset,
-
-    #[br(temp)]
     volatile_subkeys_list_offset: Offset,
-
-    #[br(temp)]
     key_values_count: u32,
 
     #[br(   if(key_values_count > 0),
@@ -59,62 +74,56 @@ pub struct KeyNode {
             args(key_values_count as usize))]
     key_values_list: Option<FilePtr32<Cell<KeyValueList, (usize,)>>>,
 
-    #[br(temp)]
     key_values_list_offset: u32,
 
-    #[br(temp)]
     key_security_offset: Offset,
 
-    #[br(temp)]
     class_name_offset: Offset,
 
-    #[br(temp)]
     max_subkey_name: u32,
-
-    #[br(temp)]
     max_subkey_class_name: u32,
-
-    #[br(temp)]
     max_value_name: u32,
-
-    #[br(temp)]
     max_value_data: u32,
-
-    #[br(temp)]
     work_var: u32,
 
     #[br(temp)]
     key_name_length: u16,
 
-    #[br(temp)]
     class_name_length: u16,
 
-    #[br(   parse_with=parse_string,
-            count=key_name_length,
-            args(flags.contains(KeyNodeFlags::KEY_COMP_NAME)))]
+    #[br(count=key_name_length)]
+    key_name_raw: Vec<u8>,
+
+    #[br(   parse_with=parse_name,
+            args(&key_name_raw, flags.contains(KeyNodeFlags::KEY_COMP_NAME), ctx.codepage()))]
     key_name_string: String,
 
     #[br(   if(key_values_count > 0 && key_values_list_offset != u32::MAX),
             parse_with=read_values,
             restore_position,
-            args(key_values_list.as_ref(), ))]
+            args(key_values_list.as_ref(), ctx))]
     values: Vec<KeyValue>,
 
     #[br(default)]
     subkeys: Rc<RefCell<Vec<Rc<RefCell<Self>>>>>,
 }
 
-fn parse_node_flags<R: Read + Seek>(
-    reader: &mut R,
-    _ro: &ReadOptions,
-    _: (),
-) -> BinResult<KeyNodeFlags> {
+/// Windows XP and Windows Server 2003 store the user flags in bits 12-15 of the flags field
+const KEY_USER_FLAGS_MASK: u16 = 0xf000;
+
+fn parse_node_flags<R: Read + Seek>(reader: &mut R, _ro: &ReadOptions, _: ()) -> BinResult<u16> {
+    let pos = reader.stream_position()?;
     let raw_value: u16 = reader.read_le()?;
-    Ok(KeyNodeFlags::from_bits(raw_value).unwrap())
+    let unknown_flags = raw_value & !(KeyNodeFlags::all().bits() | KEY_USER_FLAGS_MASK);
+    if unknown_flags != 0 {
+        log::warn!("unknown key node flags 0x{unknown_flags:04x} at offset 0x{pos:08x}");
+    }
+    Ok(raw_value)
 }
 
 bitflags! {
-    pub(crate) struct KeyNodeFlags: u16 {
+    /// flags of a [KeyNode], as documented in <https://github.com/msuhanov/regf/blob/master/Windows%20registry%20file%20format%20specification.md#flags>
+    pub struct KeyNodeFlags: u16 {
         /// This is a volatile key (not stored on disk).
         const KEY_IS_VOLATILE = 0x0001;
         /// This is the mount point of another hive (not stored on disk).
@@ -138,12 +147,124 @@ bitflags! {
     }
 }
 
+/// describes how a key in a layered hive (differencing hive) modifies the keys
+/// with the same path in the lower layers, as documented in <https://github.com/msuhanov/regf/blob/master/Windows%20registry%20file%20format%20specification.md#layered-keys>
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum LayerSemantics {
+    /// the key is merged with the keys of the lower layers
+    #[default]
+    None,
+
+    /// the key has been deleted, so that the keys of the lower layers are hidden
+    Tombstone,
+
+    /// the values of the keys of the lower layers are hidden, but their subkeys are still visible
+    SupersedeLocal,
+
+    /// the keys of the lower layers and all of their subkeys are hidden
+    SupersedeTree,
+}
+
+impl LayerSemantics {
+    /// the layer semantics are stored in bits 0-1 of the layered key bit fields,
+    /// which are stored in the second byte of the access bits
+    fn from_access_bits(access_bits: u32) -> Self {
+        match (access_bits >> 8) & 0b11 {
+            1 => Self::Tombstone,
+            2 => Self::SupersedeLocal,
+            3 => Self::SupersedeTree,
+            _ => Self::None,
+        }
+    }
+}
+
+bitflags! {
+    /// virtualization control flags of a [KeyNode], as documented in <https://github.com/msuhanov/regf/blob/master/Windows%20registry%20file%20format%20specification.md#virtualization-control-flags>
+    pub struct VirtualizationControlFlags: u8 {
+        /// This key and its subkeys should not be virtualized
+        const REG_KEY_DONT_VIRTUALIZE = 0x02;
+        /// Failed write operations in this key and its subkeys should not be silently ignored
+        const REG_KEY_DONT_SILENT_FAIL = 0x04;
+        /// Virtualization control flags of this key should be propagated to newly created subkeys
+        const REG_KEY_RECURSE_FLAG = 0x08;
+    }
+}
+
 impl KeyNode {
+    pub(crate) fn with_offset(mut self, offset: Offset) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Returns the offset of the cell which contains this key node. This offset
+    /// can be used to read this key node again, using [`Hive::key_at`].
+    pub fn offset(&self) -> Offset {
+        self.offset
+    }
+
     /// Returns the name of this Key Node.
     pub fn name(&self) -> &str {
         &self.key_name_string
     }
 
+    /// Returns the name of this key node as it is stored in the hive. Depending on
+    /// [`is_name_compressed`](Self::is_name_compressed), this is either (extended) ASCII
+    /// or UTF-16LE.
+    pub fn raw_name(&self) -> &[u8] {
+        &self.key_name_raw
+    }
+
+    /// Returns [true] if the name is stored as (extended) ASCII instead of UTF-16LE.
+    /// Such names are decoded using the [`Codepage`](crate::Codepage) of the hive.
+    pub fn is_name_compressed(&self) -> bool {
+        self.flags.contains(KeyNodeFlags::KEY_COMP_NAME)
+    }
+
+    /// Returns the name of this key node as UTF-16 code units. In contrast to
+    /// [`name`](Self::name), this keeps names which are not valid UTF-16 (e.g. which
+    /// contain unpaired surrogates) unchanged. Every byte of a compressed name is
+    /// returned as one code unit, regardless of the [`Codepage`](crate::Codepage) of the hive.
+    pub fn name_utf16(&self) -> Vec<u16> {
+        if self.is_name_compressed() {
+            self.key_name_raw.iter().map(|b| u16::from(*b)).collect()
+        } else {
+            raw_name_to_utf16(&self.key_name_raw)
+        }
+    }
+
+    /// Returns the name which is used to look up this key: compressed names are compared
+    /// after they have been decoded with the [`Codepage`](crate::Codepage) of the hive,
+    /// all other names are compared as they are stored.
+    pub(crate) fn decoded_name_utf16(&self) -> Vec<u16> {
+        if self.is_name_compressed() {
+            self.key_name_string.encode_utf16().collect()
+        } else {
+            raw_name_to_utf16(&self.key_name_raw)
+        }
+    }
+
+    /// returns [true] if this key has the name `name` (as UTF-16 code units). Keys with
+    /// [lossy](Self::is_name_lossy) names can also be found by their [`name_utf16`](Self::name_utf16).
+    fn has_name(&self, name: &[u16], comparison: NameComparison) -> bool {
+        comparison.eq_utf16(&self.decoded_name_utf16(), name)
+            || (self.is_name_lossy() && comparison.eq_utf16(&self.name_utf16(), name))
+    }
+
+    /// Returns [true] if the name could not be decoded without loss, which is the case for
+    /// names which are not valid UTF-16, and for compressed names which contain bytes that are
+    /// not defined in the [`Codepage`](crate::Codepage) of the hive. In this case,
+    /// [`name`](Self::name) contains replacement characters, and [`name_utf16`](Self::name_utf16) or
+    /// [`raw_name`](Self::raw_name) should be used to identify the key.
+    pub fn is_name_lossy(&self) -> bool {
+        if self.is_name_compressed() {
+            // no codepage maps a defined byte to the replacement character
+            self.key_name_string.contains(char::REPLACEMENT_CHARACTER)
+        } else {
+            !self.key_name_raw.len().is_multiple_of(2)
+                || String::from_utf16(&self.name_utf16()).is_err()
+        }
+    }
+
     /// Returns the time when this node has been written last.
     pub fn timestamp(&self) -> &DateTime<Utc> {
         &self.timestamp
@@ -154,13 +275,128 @@ impl KeyNode {
         self.subkey_count
     }
 
+    /// Returns the flags of this key node. Bits which are not known to this crate are
+    /// omitted, use [`raw_flags`](Self::raw_flags) to obtain the value as stored in the hive.
+    pub fn flags(&self) -> KeyNodeFlags {
+        self.flags
+    }
+
+    /// Returns the flags field as it is stored in the hive, including the user flags
+    /// of older hives and bits which are not known to this crate
+    pub fn raw_flags(&self) -> u16 {
+        self.raw_flags
+    }
+
+    /// Returns the access bits field, as it is stored in the hive. The lowest byte contains
+    /// the access bits, which are used to determine if this key has been accessed
+    /// since the hive has been loaded (Windows 8 and later). The second byte contains
+    /// the layered key bit fields (see [`layer_semantics`](Self::layer_semantics)).
+    pub fn access_bits(&self) -> u32 {
+        self.access_bits
+    }
+
+    /// Returns the layer semantics of this key, which are only used in layered hives
+    /// (also known as differencing hives, version 1.6)
+    pub fn layer_semantics(&self) -> LayerSemantics {
+        LayerSemantics::from_access_bits(self.access_bits)
+    }
+
+    /// returns [true] iff the class name of this key is inherited from the key of
+    /// the lower layer. This is only used in layered hives, and is stored in bit 7
+    /// of the layered key bit fields.
+    pub fn inherits_class(&self) -> bool {
+        (self.access_bits >> 15) & 0x01 == 0x01
+    }
+
+    /// Returns the number of volatile subkeys. This value is meaningless in hive files,
+    /// because volatile keys are not stored on disk
+    pub fn volatile_subkey_count(&self) -> u32 {
+        self.volatile_subkey_count
+    }
+
+    /// Returns the offset of the subkeys list
+    pub fn subkeys_list_offset(&self) -> Offset {
+        self.subkeys_list_offset
+    }
+
+    /// Returns the offset of the volatile subkeys list. This value is meaningless in hive files,
+    /// because volatile keys are not stored on disk
+    pub fn volatile_subkeys_list_offset(&self) -> Offset {
+        self.volatile_subkeys_list_offset
+    }
+
+    /// Returns the number of values, as it is stored in the key node. Use [`values()`](Self::values)
+    /// to obtain the values which have actually been found.
+    pub fn values_count(&self) -> u32 {
+        self.key_values_count
+    }
+
+    /// Returns the offset of the list of values
+    pub fn values_list_offset(&self) -> Offset {
+        Offset(self.key_values_list_offset)
+    }
+
+    /// Returns the length of the longest subkey name (in bytes, counted as UTF-16LE).
+    ///
+    /// Starting with Windows Vista, only the lower 16 bits of this field contain the length,
+    /// and the upper bits contain additional flags. Use [`raw_max_subkey_name`](Self::raw_max_subkey_name)
+    /// to obtain the value as stored in the hive.
+    pub fn max_subkey_name_length(&self) -> u16 {
+        (self.max_subkey_name & 0xffff) as u16
+    }
+
+    /// Returns the virtualization control flags, which are stored in bits 16-19
+    /// of the largest subkey name length
+    pub fn virtualization_control_flags(&self) -> VirtualizationControlFlags {
+        VirtualizationControlFlags::from_bits_truncate(((self.max_subkey_name >> 16) & 0x0f) as u8)
+    }
+
+    /// Returns the user flags (also known as Wow64 flags), which are stored in bits 20-23
+    /// of the largest subkey name length. Windows XP and Windows Server 2003 store them
+    /// in bits 12-15 of the [flags](Self::raw_flags) instead, which are included as well.
+    pub fn user_flags(&self) -> u8 {
+        ((self.max_subkey_name >> 20) & 0x0f) as u8 | (self.raw_flags >> 12) as u8
+    }
+
+    /// Returns the debug flags, which are stored in bits 24-31
+    /// of the largest subkey name length
+    pub fn debug(&self) -> u8 {
+        (self.max_subkey_name >> 24) as u8
+    }
+
+    /// Returns the largest subkey name length field as it is stored in the hive, including
+    /// all flags stored in its upper bits
+    pub fn raw_max_subkey_name(&self) -> u32 {
+        self.max_subkey_name
+    }
+
+    /// Returns the length of the longest subkey class name (in bytes)
+    pub fn max_subkey_class_name_length(&self) -> u32 {
+        self.max_subkey_class_name
+    }
+
+    /// Returns the length of the longest value name (in bytes, counted as UTF-16LE)
+    pub fn max_value_name_length(&self) -> u32 {
+        self.max_value_name
+    }
+
+    /// Returns the size of the largest value data (in bytes)
+    pub fn max_value_data_size(&self) -> u32 {
+        self.max_value_data
+    }
+
+    /// Returns the cached index, which is used by Windows at runtime
+    pub fn work_var(&self) -> u32 {
+        self.work_var
+    }
+
     /// Returns a list of subkeys.
     ///
     /// This function caches the subkeys, so the first call to this function might be slower.
     pub fn subkeys<B>(
         &self,
         hive: &mut Hive<B, CleanHive>,
-    ) -> BinResult<Ref<Vec<Rc<RefCell<Self>>>>>
+    ) -> Result<Ref<'_, Vec<Rc<RefCell<Self>>>>>
     where
         B: BinReaderExt,
     {
@@ -171,17 +407,62 @@ impl KeyNode {
         Ok(self.subkeys.borrow())
     }
 
-    fn read_subkeys<B>(&self, hive: &mut Hive<B, CleanHive>) -> BinResult<Vec<Rc<RefCell<Self>>>>
+    fn read_subkeys<B>(&self, hive: &mut Hive<B, CleanHive>) -> Result<Vec<Rc<RefCell<Self>>>>
+    where
+        B: BinReaderExt,
+    {
+        self.read_subkeys_lists(hive)?
+            .leafs
+            .into_iter()
+            .flat_map(|(_, subkeys_list)| subkeys_list.into_offsets())
+            .map(|offset| Ok(Rc::new(RefCell::new(hive.key_at(offset)?))))
+            .collect()
+    }
+
+    /// reads all subkeys lists of this key which refer to key nodes, together with their offsets.
+    /// Index roots are resolved, so that the result only contains leaf lists. Every list
+    /// is read only once, even if it is referenced more than once.
+    fn read_subkeys_lists<B>(&self, hive: &mut Hive<B, CleanHive>) -> Result<SubkeysLists>
     where
         B: BinReaderExt,
     {
         let offset = self.subkeys_list_offset;
+        let mut lists = SubkeysLists::default();
+
+        if offset.0 != u32::MAX {
+            Self::read_subkeys_lists_from(hive, offset, &mut Vec::new(), &mut lists)?;
+        }
+        Ok(lists)
+    }
 
-        if offset.0 == u32::MAX {
-            return Ok(Vec::new());
+    /// reads the subkeys list at `offset` and adds it to `lists`. If this is an index root,
+    /// all referenced lists are read instead. `index_roots` contains the offsets of all
+    /// index roots which refer (directly or indirectly) to the current list.
+    fn read_subkeys_lists_from<B>(
+        hive: &mut Hive<B, CleanHive>,
+        offset: Offset,
+        index_roots: &mut Vec<Offset>,
+        lists: &mut SubkeysLists,
+    ) -> Result<()>
+    where
+        B: BinReaderExt,
+    {
+        if index_roots.contains(&offset) {
+            return Err(SubkeysListError::Cycle { offset }.into());
+        }
+
+        if !lists.visited.insert(offset) {
+            log::warn!(
+                "the subkeys list at offset 0x{:08x} is referenced more than once",
+                offset.0
+            );
+            lists.repeated.push(offset);
+            return Ok(());
         }
 
         let subkeys_list: SubKeysList = hive.read_structure(offset)?;
+        let ctx = hive.parse_context();
+        subkeys_list.warn_if_unsupported(&ctx, offset);
 
         log::debug!(
             "SubKeyList is of type '{}'",
@@ -196,61 +477,311 @@ impl KeyNode {
         log::trace!("{:?}", subkeys_list);
 
         if subkeys_list.is_index_root() {
-            log::debug!("reading indirect subkey lists");
-            let subkeys: BinResult<Vec<_>> = subkeys_list
-                .into_offsets()
-                .map(|o| {
-                    let subsubkeys_list: SubKeysList = hive.read_structure(o)?;
-                    assert!(!subsubkeys_list.is_index_root());
-
-                    let subkeys: BinResult<Vec<_>> = subsubkeys_list
-                        .into_offsets()
-                        .map(|o2| {
-                            let nk: KeyNodeWithMagic = hive.read_structure(o2)?;
-                            Ok(Rc::new(RefCell::new(nk.0)))
-                        })
-                        .collect();
-                    subkeys
-                })
-                .collect();
+            let max_depth = hive.max_subkeys_list_depth();
+            if index_roots.len() >= max_depth {
+                return Err(SubkeysListError::TooDeep { offset, max_depth }.into());
+            }
+            if !index_roots.is_empty() {
+                log::warn!(
+                    "found a nested index root at offset 0x{:08x}, which is not created by Windows",
+                    offset.0
+                );
+            }
 
-            match subkeys {
-                Err(why) => Err(why),
-                Ok(sk) => Ok(sk.into_iter().flatten().collect()),
+            log::debug!("reading indirect subkey lists");
+            index_roots.push(offset);
+            for o in subkeys_list.into_offsets() {
+                Self::read_subkeys_lists_from(hive, o, index_roots, lists)?;
             }
+            index_roots.pop();
         } else {
-            log::debug!("reading single subkey list");
-            let subkeys: BinResult<Vec<_>> = subkeys_list
-                .into_offsets()
-                .map(|offset| {
-                    let nk: KeyNodeWithMagic = hive.read_structure(offset)?;
-                    Ok(Rc::new(RefCell::new(nk.0)))
-                })
-                .collect();
-            subkeys
+            lists.leafs.push((offset, subkeys_list));
+        }
+        Ok(())
+    }
+
+    /// checks if the subkeys lists of this key follow the rules which Windows expects:
+    /// the subkeys must be sorted by their upper case names, hashes and hints must match
+    /// the names of the subkeys, every subkeys list and every subkey must be referenced only once, there
+    /// must be no two subkeys with the same name, the number of subkeys must match
+    /// [`subkey_count`](Self::subkey_count), and the types of the subkeys lists must be
+    /// supported by the format version of the hive. Windows might not find subkeys if one of
+    /// these rules is violated.
+    ///
+    /// Returns an empty list if no issues have been found.
+    pub fn verify_subkeys_lists<B>(
+        &self,
+        hive: &mut Hive<B, CleanHive>,
+    ) -> Result<Vec<SubkeysListIssue>>
+    where
+        B: BinReaderExt,
+    {
+        let lists = self.read_subkeys_lists(hive)?;
+        let ctx = hive.parse_context();
+        let mut issues: Vec<_> = lists
+            .repeated
+            .into_iter()
+            .map(|list_offset| SubkeysListIssue::DuplicateList { list_offset })
+            .collect();
+        issues.extend(
+            lists
+                .leafs
+                .iter()
+                .filter(|(_, subkeys_list)| !subkeys_list.is_supported_by(&ctx))
+                .map(|(list_offset, _)| SubkeysListIssue::UnsupportedListType {
+                    list_offset: *list_offset,
+                    minor_version: ctx.minor_version(),
+                }),
+        );
+        let mut seen_offsets = HashSet::new();
+        let mut seen_names = HashSet::new();
+        let mut previous: Option<(Vec<u16>, String)> = None;
+        let mut found = 0;
+
+        for (list_offset, subkeys_list) in lists.leafs {
+            for (index, key_node_offset) in subkeys_list.offsets().enumerate() {
+                found += 1;
+                if !seen_offsets.insert(key_node_offset) {
+                    issues.push(SubkeysListIssue::DuplicateOffset {
+                        list_offset,
+                        key_node_offset,
+                    });
+                    continue;
+                }
+
+                let subkey = match hive.key_at(key_node_offset) {
+                    Ok(subkey) => subkey,
+                    Err(why) => {
+                        issues.push(SubkeysListIssue::UnreadableSubkey {
+                            list_offset,
+                            key_node_offset,
+                            error: why.to_string(),
+                        });
+                        continue;
+                    }
+                };
+                let name = subkey.name();
+                let name_utf16 = subkey.name_utf16();
+
+                issues.extend(subkeys_list.check_entry(list_offset, index, &name_utf16));
+
+                let upcase_name: Vec<u16> = name_utf16.into_iter().map(nt_upcase).collect();
+                if !seen_names.insert(upcase_name.clone()) {
+                    issues.push(SubkeysListIssue::DuplicateName {
+                        list_offset,
+                        key_node_offset,
+                        name: name.to_string(),
+                    });
+                } else if let Some((previous_upcase_name, previous_name)) = &previous {
+                    if *previous_upcase_name > upcase_name {
+                        issues.push(SubkeysListIssue::OutOfOrder {
+                            list_offset,
+                            key_node_offset,
+                            previous_name: previous_name.clone(),
+                            name: name.to_string(),
+                        });
+                    }
+                }
+                previous = Some((upcase_name, name.to_string()));
+            }
         }
+
+        if found != self.subkey_count() as usize {
+            issues.push(SubkeysListIssue::WrongCount {
+                declared: self.subkey_count(),
+                found,
+            });
+        }
+
+        Ok(issues)
     }
 
     fn subpath_parts<B>(
         &self,
-        mut path_parts: Vec<&str>,
+        mut path_parts: Vec<Vec<u16>>,
         hive: &mut Hive<B, CleanHive>,
-    ) -> BinResult<Option<Rc<RefCell<Self>>>>
+        mode: SymlinkMode,
+        visited_links: &mut Vec<String>,
+    ) -> Result<Option<Rc<RefCell<Self>>>>
     where
         B: BinReaderExt,
     {
         if let Some(first) = path_parts.pop() {
-            if let Some(top) = self.subkey(first, hive)? {
+            if let Some(top) = self.subkey_utf16(&first, hive)? {
+                let top = match mode {
+                    SymlinkMode::DontFollow => top,
+                    SymlinkMode::Follow => match Self::resolve_link(top, hive, visited_links)? {
+                        Some(target) => target,
+                        None => return Ok(None),
+                    },
+                };
                 return if path_parts.is_empty() {
                     Ok(Some(top))
                 } else {
-                    top.borrow().subpath_parts(path_parts, hive)
+                    top.borrow()
+                        .subpath_parts(path_parts, hive, mode, visited_links)
                 };
             }
         }
         Ok(None)
     }
 
+    /// returns the key which is referenced by `key`, if `key` is a symbolic link.
+    /// Otherwise, `key` is returned.
+    ///
+    /// `visited_links` contains the targets of all links which are currently being resolved.
+    /// Because the target of a link might contain other links, finding the target again
+    /// means that there is a loop. After a link has been resolved, its target is removed
+    /// again, so that a path might contain several links with the same target.
+    fn resolve_link<B>(
+        key: Rc<RefCell<Self>>,
+        hive: &mut Hive<B, CleanHive>,
+        visited_links: &mut Vec<String>,
+    ) -> Result<Option<Rc<RefCell<Self>>>>
+    where
+        B: BinReaderExt,
+    {
+        if !key.borrow().is_symbolic_link() {
+            return Ok(Some(key));
+        }
+
+        let target = match key.borrow().symbolic_link_target() {
+            Some(target) => target.to_string(),
+            None => {
+                log::warn!(
+                    "key '{}' is a symbolic link, but has no link target",
+                    key.borrow().name()
+                );
+                return Ok(None);
+            }
+        };
+
+        let normalized_target = target.to_lowercase();
+        if visited_links.contains(&normalized_target) {
+            return Err(Error::SymbolicLinkLoop {
+                offset: key.borrow().offset(),
+                target,
+            });
+        }
+        let chain_length = visited_links.len();
+        visited_links.push(normalized_target);
+        let offset = key.borrow().offset();
+        let result = Self::resolve_link_target(offset, &target, hive, visited_links);
+        visited_links.truncate(chain_length);
+        result
+    }
+
+    fn resolve_link_target<B>(
+        offset: Offset,
+        target: &str,
+        hive: &mut Hive<B, CleanHive>,
+        visited_links: &mut Vec<String>,
+    ) -> Result<Option<Rc<RefCell<Self>>>>
+    where
+        B: BinReaderExt,
+    {
+        let comparison = hive.name_comparison();
+        let path_parts = match hive
+            .mount_point()
+            .and_then(|mount_point| link_target_in_hive(target, mount_point, comparison))
+        {
+            Some(path_parts) => path_parts,
+            None => {
+                return Err(Error::SymbolicLinkIntoOtherHive {
+                    offset,
+                    target: target.to_string(),
+                })
+            }
+        };
+
+        let root_key = hive.root_key_node()?;
+        if path_parts.is_empty() {
+            Ok(Some(Rc::new(RefCell::new(root_key))))
+        } else {
+            let path_parts = path_parts.into_iter().rev().map(utf16).collect();
+            root_key.subpath_parts(path_parts, hive, SymlinkMode::Follow, visited_links)
+        }
+    }
+
+    /// returns [true] if this key is the root key of `hive`
+    fn is_root_of<B>(&self, hive: &Hive<B, CleanHive>) -> bool
+    where
+        B: BinReaderExt,
+    {
+        self.flags.contains(KeyNodeFlags::KEY_HIVE_ENTRY)
+            || hive.root_cell_offset().ok() == Some(self.offset)
+    }
+
+    /// returns the parent of this key, or [`None`] if this is the root key
+    pub fn parent_node<B>(&self, hive: &mut Hive<B, CleanHive>) -> Result<Option<KeyNode>>
+    where
+        B: BinReaderExt,
+    {
+        if self.is_root_of(hive) {
+            Ok(None)
+        } else {
+            hive.key_at(self.parent).map(Some)
+        }
+    }
+
+    /// reconstructs the full path of this key by following the parent offsets up to the
+    /// root key. If the chain of parents is damaged (the parent cannot be read or there
+    /// is a loop), an incomplete path is returned, see [KeyPath].
+    pub fn path<B>(&self, hive: &mut Hive<B, CleanHive>) -> KeyPath
+    where
+        B: BinReaderExt,
+    {
+        let mut components = vec![self.name().to_string()];
+        let mut visited = HashSet::from([self.offset]);
+        let mut is_root = self.is_root_of(hive);
+        let mut parent = self.parent;
+
+        let termination = loop {
+            if is_root {
+                break PathTermination::Root;
+            }
+            if !visited.insert(parent) {
+                log::warn!("loop detected in the parents of key '{}'", self.name());
+                break PathTermination::Loop(parent);
+            }
+            match hive.key_at(parent) {
+                Ok(parent_node) => {
+                    components.push(parent_node.name().to_string());
+                    is_root = parent_node.is_root_of(hive);
+                    parent = parent_node.parent;
+                }
+                Err(why) => {
+                    log::warn!("unable to read parent key at 0x{:08x}: {why}", parent.0);
+                    break PathTermination::UnreachableParent(parent);
+                }
+            }
+        };
+
+        components.reverse();
+        KeyPath::new(components, termination)
+    }
+
+    /// returns [true] iff this key is a symbolic link
+    pub fn is_symbolic_link(&self) -> bool {
+        self.flags.contains(KeyNodeFlags::KEY_SYM_LINK)
+    }
+
+    /// returns the target of this key, if this key is a symbolic link. The target is
+    /// stored in the `SymbolicLinkValue` value, and is an absolute path such as
+    /// `\REGISTRY\MACHINE\SYSTEM\ControlSet001`
+    pub fn symbolic_link_target(&self) -> Option<&str> {
+        if !self.is_symbolic_link() {
+            return None;
+        }
+        self.values
+            .iter()
+            .filter(|v| v.name().eq_ignore_ascii_case(SYMBOLIC_LINK_VALUE))
+            .find_map(|v| match v.value() {
+                RegistryValue::RegLink(target) => Some(&target[..]),
+                _ => None,
+            })
+    }
+
     /// returns the subkey with a given `name`, or [`None`] if there is no such subkey.
     /// The name is compared without case sensitivity, because
     ///
@@ -259,27 +790,157 @@ impl KeyNode {
     /// > but any other printable character can be used. Value names and data can include the backslash character.
     ///
     /// (<https://learn.microsoft.com/en-us/windows/win32/sysinfo/structure-of-the-registry>)
+    ///
+    /// Use [`Hive::set_name_comparison`] to specify how the case of non-ASCII characters is ignored.
+    ///
+    /// If the subkeys have not been read yet, only those key nodes are read whose name
+    /// hash (or name hint, respectively) matches `name`. In this case, the subkeys are not cached.
     pub fn subkey<B>(
         &self,
         name: &str,
         hive: &mut Hive<B, CleanHive>,
-    ) -> BinResult<Option<Rc<RefCell<Self>>>>
+    ) -> Result<Option<Rc<RefCell<Self>>>>
     where
         B: BinReaderExt,
     {
-        let lowercase_name = name.to_lowercase();
-        let subkey = self
-            .subkeys(hive)?
-            .iter()
-            .find(|s| s.borrow().name().to_lowercase() == lowercase_name)
-            .cloned();
-        Ok(subkey)
+        let name: Vec<u16> = name.encode_utf16().collect();
+        self.subkey_utf16(&name, hive)
     }
 
-    /// returns the list of all [KeyValue]s of this key
+    /// same as [`subkey`](Self::subkey), but expects the name as UTF-16 code units. This allows
+    /// to find keys whose names are not valid UTF-16, see [`name_utf16`](Self::name_utf16).
+    /// Compressed names are compared after they have been decoded with the codepage of the hive.
+    pub fn subkey_utf16<B>(
+        &self,
+        name: &[u16],
+        hive: &mut Hive<B, CleanHive>,
+    ) -> Result<Option<Rc<RefCell<Self>>>>
+    where
+        B: BinReaderExt,
+    {
+        let comparison = hive.name_comparison();
+
+        // if the subkeys have already been read, there is no need to read them again
+        if !self.subkeys.borrow().is_empty() {
+            let subkey = self
+                .subkeys
+                .borrow()
+                .iter()
+                .find(|s| s.borrow().has_name(name, comparison))
+                .cloned();
+            return Ok(subkey);
+        }
+
+        // otherwise, we use the name hashes and hints stored in the subkeys lists,
+        // so that we only need to read the key nodes which might match. Hashes and hints
+        // of compressed names are computed over their raw bytes
+        let mut stored_names = vec![name.to_vec()];
+        let codepage = hive.codepage();
+        if codepage != Codepage::Latin1 {
+            let encoded = String::from_utf16(name)
+                .ok()
+                .and_then(|name| codepage.encode(&name));
+            if let Some(encoded) = encoded {
+                stored_names.push(encoded.into_iter().map(u16::from).collect());
+            }
+        }
+
+        for (_, subkeys_list) in self.read_subkeys_lists(hive)?.leafs {
+            for offset in subkeys_list.candidate_offsets(&stored_names, comparison) {
+                let subkey = hive.key_at(offset)?;
+                if subkey.has_name(name, comparison) {
+                    return Ok(Some(Rc::new(RefCell::new(subkey))));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// returns the list of all [KeyValue]s of this key. This list contains an entry for
+    /// every offset in the value list of this key, even if the value could not be read.
+    /// Such values are marked as [invalid](KeyValue::is_invalid).
     pub fn values(&self) -> &Vec<KeyValue> {
         &self.values
     }
+
+    /// reads the class name of this key, or returns [`None`] if this key has no class name.
+    ///
+    /// The class name is read directly from its cell, without checking if this cell
+    /// is allocated, because class names are sometimes found in deleted cells. If the cell
+    /// is larger than needed, only the first bytes (as specified in the key node) are used.
+    /// If the cell is too small, [`Error::CellTooSmall`] is returned.
+    pub fn class_name<B>(&self, hive: &mut Hive<B, CleanHive>) -> Result<Option<String>>
+    where
+        B: BinReaderExt,
+    {
+        if self.class_name_offset.0 == u32::MAX || self.class_name_length == 0 {
+            return Ok(None);
+        }
+
+        hive.seek(SeekFrom::Start(self.class_name_offset.0.into()))?;
+        let mut cell: CellWithU8List = hive.read_le_args((None,))?;
+        let class_name_length = usize::from(self.class_name_length);
+        if cell.data.len() < class_name_length {
+            return Err(Error::CellTooSmall {
+                offset: self.class_name_offset,
+                expected: class_name_length,
+                found: cell.data.len(),
+            });
+        }
+        cell.data.truncate(class_name_length);
+
+        if cell.is_deleted() {
+            log::debug!(
+                "class name of key '{}' is stored in a deleted cell at 0x{:08x}",
+                self.name(),
+                self.class_name_offset.0
+            );
+        }
+        Ok(Some(parse_reg_sz_raw(&cell.data, hive.codepage())?))
+    }
+
+    /// returns the offset of the key security item of this key
+    pub fn security_offset(&self) -> Offset {
+        self.key_security_offset
+    }
+
+    /// reads the key security item of this key, which contains the security descriptor
+    /// (owner, group, DACL and SACL) of this key.
+    pub fn security_descriptor<B>(&self, hive: &mut Hive<B, CleanHive>) -> Result<KeySecurity>
+    where
+        B: BinReaderExt,
+    {
+        let ksm: KeySecurityWithMagic = hive.read_structure(self.key_security_offset)?;
+        Ok(ksm.into())
+    }
+}
+
+/// the subkeys lists of a key node, as they are found while traversing its index roots
+#[derive(Default)]
+struct SubkeysLists {
+    /// all lists which refer to key nodes, together with their offsets
+    leafs: Vec<(Offset, SubKeysList)>,
+
+    /// the offsets of all lists which have been read
+    visited: HashSet<Offset>,
+
+    /// the offsets of all lists which are referenced more than once
+    repeated: Vec<Offset>,
+}
+
+/// specifies if symbolic links should be followed when looking up a path with [SubPath]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SymlinkMode {
+    /// return keys which are symbolic links as they are
+    #[default]
+    DontFollow,
+
+    /// replace every key which is a symbolic link by the key it points to.
+    ///
+    /// Link targets are resolved relative to the mount point of the current hive (see
+    /// [`Hive::set_mount_point`]). Links whose target is not below this mount point
+    /// point into other hives and result in [`Error::SymbolicLinkIntoOtherHive`].
+    Follow,
 }
 
 pub trait SubPath<T> {
@@ -287,71 +948,127 @@ pub trait SubPath<T> {
         &self,
         path: T,
         hive: &mut Hive<B, CleanHive>,
-    ) -> BinResult<Option<Rc<RefCell<Self>>>>
+    ) -> Result<Option<Rc<RefCell<Self>>>>
+    where
+        B: BinReaderExt,
+    {
+        self.subpath_with_mode(path, hive, SymlinkMode::default())
+    }
+
+    fn subpath_with_mode<B>(
+        &self,
+        path: T,
+        hive: &mut Hive<B, CleanHive>,
+        mode: SymlinkMode,
+    ) -> Result<Option<Rc<RefCell<Self>>>>
     where
         B: BinReaderExt;
 }
 
 impl SubPath<&str> for KeyNode {
-    fn subpath<B>(
+    fn subpath_with_mode<B>(
         &self,
         path: &str,
         hive: &mut Hive<B, CleanHive>,
-    ) -> BinResult<Option<Rc<RefCell<Self>>>>
+        mode: SymlinkMode,
+    ) -> Result<Option<Rc<RefCell<Self>>>>
     where
         B: BinReaderExt,
     {
-        let path_parts: Vec<_> = path.split('\\').rev().collect();
-        self.subpath_parts(path_parts, hive)
+        let path_parts: Vec<_> = path.split('\\').rev().map(utf16).collect();
+        self.subpath_parts(path_parts, hive, mode, &mut Vec::new())
     }
 }
 
 impl SubPath<&String> for KeyNode {
-    fn subpath<B>(
+    fn subpath_with_mode<B>(
         &self,
         path: &String,
         hive: &mut Hive<B, CleanHive>,
-    ) -> BinResult<Option<Rc<RefCell<Self>>>>
+        mode: SymlinkMode,
+    ) -> Result<Option<Rc<RefCell<Self>>>>
     where
         B: BinReaderExt,
     {
-        let path_parts: Vec<_> = path.split('\\').rev().collect();
-        self.subpath_parts(path_parts, hive)
+        let path_parts: Vec<_> = path.split('\\').rev().map(utf16).collect();
+        self.subpath_parts(path_parts, hive, mode, &mut Vec::new())
     }
 }
 
 impl SubPath<&Vec<&str>> for KeyNode {
-    fn subpath<B>(
+    fn subpath_with_mode<B>(
         &self,
         path: &Vec<&str>,
         hive: &mut Hive<B, CleanHive>,
-    ) -> BinResult<Option<Rc<RefCell<Self>>>>
+        mode: SymlinkMode,
+    ) -> Result<Option<Rc<RefCell<Self>>>>
     where
         B: BinReaderExt,
     {
-        let path_parts: Vec<_> = path.iter().rev().copied().collect();
-        self.subpath_parts(path_parts, hive)
+        let path_parts: Vec<_> = path.iter().rev().map(|s| utf16(s)).collect();
+        self.subpath_parts(path_parts, hive, mode, &mut Vec::new())
     }
 }
 
 impl SubPath<&Vec<String>> for KeyNode {
-    fn subpath<B>(
+    fn subpath_with_mode<B>(
         &self,
         path: &Vec<String>,
         hive: &mut Hive<B, CleanHive>,
-    ) -> BinResult<Option<Rc<RefCell<Self>>>>
+        mode: SymlinkMode,
+    ) -> Result<Option<Rc<RefCell<Self>>>>
+    where
+        B: BinReaderExt,
+    {
+        let path_parts: Vec<_> = path.iter().rev().map(|s| utf16(s)).collect();
+        self.subpath_parts(path_parts, hive, mode, &mut Vec::new())
+    }
+}
+
+/// allows to find keys whose names are not valid UTF-16. Every element of `path`
+/// contains the name of one key as UTF-16 code units, see [`KeyNode::name_utf16`]
+impl SubPath<&[Vec<u16>]> for KeyNode {
+    fn subpath_with_mode<B>(
+        &self,
+        path: &[Vec<u16>],
+        hive: &mut Hive<B, CleanHive>,
+        mode: SymlinkMode,
+    ) -> Result<Option<Rc<RefCell<Self>>>>
     where
         B: BinReaderExt,
     {
-        let path_parts: Vec<_> = path.iter().rev().map(|s| &s[..]).collect();
-        self.subpath_parts(path_parts, hive)
+        let path_parts: Vec<_> = path.iter().rev().cloned().collect();
+        self.subpath_parts(path_parts, hive, mode, &mut Vec::new())
+    }
+}
+
+fn utf16(s: &str) -> Vec<u16> {
+    s.encode_utf16().collect()
+}
+
+const SYMBOLIC_LINK_VALUE: &str = "SymbolicLinkValue";
+
+/// converts the absolute target of a symbolic link (such as `\REGISTRY\MACHINE\SYSTEM\ControlSet001`)
+/// into a path relative to the root of the hive which is mounted at `mount_point` (such as
+/// `ControlSet001`). Returns [`None`] if the target is not below `mount_point`.
+fn link_target_in_hive<'t>(
+    target: &'t str,
+    mount_point: &str,
+    comparison: NameComparison,
+) -> Option<Vec<&'t str>> {
+    let mut parts = target.split('\\').filter(|p| !p.is_empty());
+    for mount_part in mount_point.split('\\').filter(|p| !p.is_empty()) {
+        if !comparison.eq(parts.next()?, mount_part) {
+            return None;
+        }
     }
+    Some(parts.collect())
 }
 
 fn read_values<R: Read + Seek>(
     reader: &mut R,
     _ro: &ReadOptions,
-    args: (Option<&FilePtr32<KeyValueCell>>,),
+    args: (Option<&FilePtr32<KeyValueCell>>, ParseContext),
 ) -> BinResult<Vec<KeyValue>> {
     Ok(match args.0 {
         None => Vec::new(),
@@ -362,11 +1079,13 @@ fn read_values<R: Read + Seek>(
                 let mut result = Vec::with_capacity(kv_list.key_value_offsets.len());
                 for offset in kv_list.key_value_offsets.iter() {
                     reader.seek(SeekFrom::Start(offset.0.into()))?;
-                    let vk_result: BinResult<Cell<KeyValueWithMagic, ()>> = reader.read_le();
+                    let vk_result: BinResult<Cell<KeyValueWithMagic, (ParseContext,)>> =
+                        reader.read_le_args((args.1,));
                     match vk_result {
-                        Ok(vk) => result.push(vk.into()),
+                        Ok(vk) => result.push(KeyValue::from(vk).with_offset(*offset)),
                         Err(why) => {
                             log::debug!("error while parsing KeyValue: {}", why);
+                            result.push(KeyValue::invalid(*offset, why.to_string()));
                         }
                     }
                 }
@@ -376,8 +1095,8 @@ fn read_values<R: Read + Seek>(
     })
 }
 
-impl From<Cell<KeyNodeWithMagic, ()>> for KeyNodeWithMagic {
-    fn from(cell: Cell<KeyNodeWithMagic, ()>) -> Self {
+impl From<Cell<KeyNodeWithMagic, (ParseContext,)>> for KeyNodeWithMagic {
+    fn from(cell: Cell<KeyNodeWithMagic, (ParseContext,)>) -> Self {
         cell.into_data()
     }
 }
@@ -387,3 +1106,81 @@ impl From<KeyNodeWithMagic> for KeyNode {
         mkn.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_semantics() {
+        assert_eq!(
+            LayerSemantics::from_access_bits(0x0000_0002),
+            LayerSemantics::None
+        );
+        assert_eq!(
+            LayerSemantics::from_access_bits(0x0000_c000),
+            LayerSemantics::None
+        );
+        assert_eq!(
+            LayerSemantics::from_access_bits(0x0000_0100),
+            LayerSemantics::Tombstone
+        );
+        assert_eq!(
+            LayerSemantics::from_access_bits(0x0000_0202),
+            LayerSemantics::SupersedeLocal
+        );
+        assert_eq!(
+            LayerSemantics::from_access_bits(0x0000_8300),
+            LayerSemantics::SupersedeTree
+        );
+    }
+
+    #[test]
+    fn test_link_target_in_hive() {
+        let nt = NameComparison::NtUpcase;
+        assert_eq!(
+            link_target_in_hive(
+                "\\REGISTRY\\MACHINE\\SYSTEM\\ControlSet001",
+                "\\REGISTRY\\MACHINE\\SYSTEM",
+                nt
+            ),
+            Some(vec!["ControlSet001"])
+        );
+        assert_eq!(
+            link_target_in_hive(
+                "\\Registry\\User\\S-1-5-18\\Software\\Classes",
+                "\\REGISTRY\\USER\\S-1-5-18",
+                nt
+            ),
+            Some(vec!["Software", "Classes"])
+        );
+        assert_eq!(
+            link_target_in_hive(
+                "\\REGISTRY\\MACHINE\\SYSTEM",
+                "\\REGISTRY\\MACHINE\\SYSTEM",
+                nt
+            ),
+            Some(vec![])
+        );
+        assert_eq!(
+            link_target_in_hive(
+                "\\REGISTRY\\MACHINE\\SOFTWARE\\Classes",
+                "\\REGISTRY\\MACHINE\\SYSTEM",
+                nt
+            ),
+            None
+        );
+        assert_eq!(
+            link_target_in_hive("\\REGISTRY\\MACHINE", "\\REGISTRY\\MACHINE\\SYSTEM", nt),
+            None
+        );
+        assert_eq!(
+            link_target_in_hive(
+                "\\Device\\HarddiskVolume1",
+                "\\REGISTRY\\MACHINE\\SYSTEM",
+                nt
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,892 @@
+//! decoders for the hardware resource descriptions stored as `REG_RESOURCE_LIST`,
+//! `REG_FULL_RESOURCE_DESCRIPTOR` and `REG_RESOURCE_REQUIREMENTS_LIST` values.
+//!
+//! The structures are documented in
+//! <https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/ns-wdm-_cm_resource_list> and
+//! <https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/ns-wdm-_io_resource_requirements_list>
+
+use std::fmt::Display;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use binread::{BinReaderExt, BinResult};
+use num_traits::FromPrimitive;
+
+use crate::Result;
+
+/// The size of `CM_PARTIAL_RESOURCE_DESCRIPTOR` depends on the architecture of the system
+/// which wrote the data, because the interrupt affinity is pointer sized.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum DescriptorLayout {
+    X86,
+    X64,
+}
+
+impl DescriptorLayout {
+    fn union_size(&self) -> usize {
+        match self {
+            Self::X86 => 12,
+            Self::X64 => 16,
+        }
+    }
+}
+
+/// tries to parse `raw_value` using all known layouts, and prefers the layout which
+/// consumes exactly all bytes
+fn parse_with_layout<T, F>(raw_value: &[u8], parser: F) -> BinResult<T>
+where
+    F: Fn(&mut Cursor<&[u8]>, DescriptorLayout) -> BinResult<T>,
+{
+    let parse = |layout| {
+        let mut cursor = Cursor::new(raw_value);
+        let result = parser(&mut cursor, layout);
+        let is_complete = result.is_ok() && cursor.position() == raw_value.len() as u64;
+        (result, is_complete)
+    };
+
+    match parse(DescriptorLayout::X64) {
+        (result, true) => result,
+        (first_result, false) => match parse(DescriptorLayout::X86) {
+            (result, true) => result,
+            _ => first_result,
+        },
+    }
+}
+
+/// returns the number of bytes which can still be read from `reader`
+fn remaining_bytes<R: Read + Seek>(reader: &mut R) -> std::io::Result<u64> {
+    let pos = reader.stream_position()?;
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(pos))?;
+    Ok(end.saturating_sub(pos))
+}
+
+/// bus types, as defined by the `INTERFACE_TYPE` enumeration
+#[derive(Debug, Clone, Copy, Eq, PartialEq, num_derive::FromPrimitive)]
+pub enum InterfaceType {
+    Undefined = -1,
+    Internal = 0,
+    Isa = 1,
+    Eisa = 2,
+    MicroChannel = 3,
+    TurboChannel = 4,
+    PciBus = 5,
+    VmeBus = 6,
+    NuBus = 7,
+    PcmciaBus = 8,
+    CBus = 9,
+    MpiBus = 10,
+    MpsaBus = 11,
+    ProcessorInternal = 12,
+    InternalPowerBus = 13,
+    PnpIsaBus = 14,
+    PnpBus = 15,
+    Vmcs = 16,
+    AcpiBus = 17,
+}
+
+fn fmt_interface_type(raw_interface_type: i32) -> String {
+    match InterfaceType::from_i32(raw_interface_type) {
+        Some(interface_type) => format!("{interface_type:?}"),
+        None => format!("InterfaceType({raw_interface_type})"),
+    }
+}
+
+/// resource types, as defined by the `CmResourceType*` constants
+#[derive(Debug, Clone, Copy, Eq, PartialEq, num_derive::FromPrimitive)]
+pub enum ResourceType {
+    Null = 0,
+    Port = 1,
+    Interrupt = 2,
+    Memory = 3,
+    Dma = 4,
+    DeviceSpecific = 5,
+    BusNumber = 6,
+    MemoryLarge = 7,
+    ConfigData = 128,
+    DevicePrivate = 129,
+    PcCardConfig = 130,
+    MfCardConfig = 131,
+    Connection = 132,
+}
+
+/// specifies if a resource can be shared, as defined by the `CM_SHARE_DISPOSITION` enumeration
+#[derive(Debug, Clone, Copy, Eq, PartialEq, num_derive::FromPrimitive)]
+pub enum ShareDisposition {
+    Undetermined = 0,
+    DeviceExclusive = 1,
+    DriverExclusive = 2,
+    Shared = 3,
+}
+
+const CM_RESOURCE_MEMORY_LARGE_40: u16 = 0x0200;
+const CM_RESOURCE_MEMORY_LARGE_48: u16 = 0x0400;
+const CM_RESOURCE_MEMORY_LARGE_64: u16 = 0x0800;
+
+/// represents a `CM_RESOURCE_LIST`, which is stored as `REG_RESOURCE_LIST`
+#[derive(Debug, Clone)]
+pub struct ResourceList {
+    descriptors: Vec<FullResourceDescriptor>,
+}
+
+impl ResourceList {
+    /// parses the data of a `REG_RESOURCE_LIST` value
+    pub fn parse(raw_value: &[u8]) -> Result<Self> {
+        let result = parse_with_layout(raw_value, |reader, layout| Self::read(reader, layout))?;
+        Ok(result)
+    }
+
+    fn read<R: Read + Seek>(reader: &mut R, layout: DescriptorLayout) -> BinResult<Self> {
+        let count: u32 = reader.read_le()?;
+        let descriptors = (0..count)
+            .map(|_| FullResourceDescriptor::read(reader, layout))
+            .collect::<BinResult<Vec<_>>>()?;
+        Ok(Self { descriptors })
+    }
+
+    /// Returns the resource descriptors of all buses in this list
+    pub fn descriptors(&self) -> &Vec<FullResourceDescriptor> {
+        &self.descriptors
+    }
+}
+
+impl Display for ResourceList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, descriptor) in self.descriptors.iter().enumerate() {
+            if idx > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{descriptor}")?;
+        }
+        Ok(())
+    }
+}
+
+/// represents a `CM_FULL_RESOURCE_DESCRIPTOR`, which is stored as `REG_FULL_RESOURCE_DESCRIPTOR`
+#[derive(Debug, Clone)]
+pub struct FullResourceDescriptor {
+    raw_interface_type: i32,
+    bus_number: u32,
+    version: u16,
+    revision: u16,
+    partial_descriptors: Vec<PartialResourceDescriptor>,
+}
+
+impl FullResourceDescriptor {
+    /// parses the data of a `REG_FULL_RESOURCE_DESCRIPTOR` value
+    pub fn parse(raw_value: &[u8]) -> Result<Self> {
+        let result = parse_with_layout(raw_value, |reader, layout| Self::read(reader, layout))?;
+        Ok(result)
+    }
+
+    fn read<R: Read + Seek>(reader: &mut R, layout: DescriptorLayout) -> BinResult<Self> {
+        let raw_interface_type = reader.read_le()?;
+        let bus_number = reader.read_le()?;
+        let version = reader.read_le()?;
+        let revision = reader.read_le()?;
+        let count: u32 = reader.read_le()?;
+        let partial_descriptors = (0..count)
+            .map(|_| PartialResourceDescriptor::read(reader, layout))
+            .collect::<BinResult<Vec<_>>>()?;
+        Ok(Self {
+            raw_interface_type,
+            bus_number,
+            version,
+            revision,
+            partial_descriptors,
+        })
+    }
+
+    /// Returns the bus type, or [`None`] if the bus type is unknown
+    pub fn interface_type(&self) -> Option<InterfaceType> {
+        InterfaceType::from_i32(self.raw_interface_type)
+    }
+
+    /// Returns the bus type as it is stored in the hive
+    pub fn raw_interface_type(&self) -> i32 {
+        self.raw_interface_type
+    }
+
+    /// Returns the system-assigned number of the bus
+    pub fn bus_number(&self) -> u32 {
+        self.bus_number
+    }
+
+    /// Returns the version of the partial resource list
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Returns the revision of the partial resource list
+    pub fn revision(&self) -> u16 {
+        self.revision
+    }
+
+    /// Returns the resources assigned on this bus
+    pub fn partial_descriptors(&self) -> &Vec<PartialResourceDescriptor> {
+        &self.partial_descriptors
+    }
+}
+
+impl Display for FullResourceDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} bus {}: [",
+            fmt_interface_type(self.raw_interface_type),
+            self.bus_number
+        )?;
+        for (idx, descriptor) in self.partial_descriptors.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{descriptor}")?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// represents a `CM_PARTIAL_RESOURCE_DESCRIPTOR`
+#[derive(Debug, Clone)]
+pub struct PartialResourceDescriptor {
+    raw_resource_type: u8,
+    raw_share_disposition: u8,
+    flags: u16,
+    resource: PartialResource,
+}
+
+/// the type specific part of a [PartialResourceDescriptor]
+#[derive(Debug, Clone)]
+pub enum PartialResource {
+    Port {
+        start: u64,
+        length: u32,
+    },
+    Interrupt {
+        level: u32,
+        vector: u32,
+        affinity: u64,
+    },
+    Memory {
+        start: u64,
+        length: u64,
+    },
+    Dma {
+        channel: u32,
+        port: u32,
+    },
+    DeviceSpecific(Vec<u8>),
+    BusNumber {
+        start: u32,
+        length: u32,
+    },
+
+    /// resource whose type is not (yet) supported, containing its raw data
+    Unsupported(Vec<u8>),
+}
+
+impl PartialResourceDescriptor {
+    fn read<R: Read + Seek>(reader: &mut R, layout: DescriptorLayout) -> BinResult<Self> {
+        let raw_resource_type: u8 = reader.read_le()?;
+        let raw_share_disposition = reader.read_le()?;
+        let flags: u16 = reader.read_le()?;
+
+        let mut union_data = vec![0; layout.union_size()];
+        reader.read_exact(&mut union_data)?;
+        let mut data = Cursor::new(&union_data[..]);
+
+        let resource = match ResourceType::from_u8(raw_resource_type) {
+            Some(ResourceType::Port) => PartialResource::Port {
+                start: data.read_le()?,
+                length: data.read_le()?,
+            },
+            Some(ResourceType::Interrupt) => PartialResource::Interrupt {
+                level: data.read_le()?,
+                vector: data.read_le()?,
+                affinity: match layout {
+                    DescriptorLayout::X86 => data.read_le::<u32>()?.into(),
+                    DescriptorLayout::X64 => data.read_le()?,
+                },
+            },
+            Some(ResourceType::Memory) => PartialResource::Memory {
+                start: data.read_le()?,
+                length: data.read_le::<u32>()?.into(),
+            },
+            Some(ResourceType::MemoryLarge) => {
+                let start = data.read_le()?;
+                let length: u64 = data.read_le::<u32>()?.into();
+                let length = if flags & CM_RESOURCE_MEMORY_LARGE_40 != 0 {
+                    length << 8
+                } else if flags & CM_RESOURCE_MEMORY_LARGE_48 != 0 {
+                    length << 16
+                } else if flags & CM_RESOURCE_MEMORY_LARGE_64 != 0 {
+                    length << 32
+                } else {
+                    length
+                };
+                PartialResource::Memory { start, length }
+            }
+            Some(ResourceType::Dma) => PartialResource::Dma {
+                channel: data.read_le()?,
+                port: data.read_le()?,
+            },
+            Some(ResourceType::DeviceSpecific) => {
+                // the device specific data follows directly after this descriptor
+                let data_size: u32 = data.read_le()?;
+
+                // don't trust the size before we know that there is enough data
+                let remaining = remaining_bytes(reader)?;
+                if u64::from(data_size) > remaining {
+                    return Err(binread::Error::AssertFail {
+                        pos: reader.stream_position()?,
+                        message: format!(
+                            "device specific data of {data_size} bytes expected, but only {remaining} bytes are left"
+                        ),
+                    });
+                }
+                let mut device_data = vec![0; data_size as usize];
+                reader.read_exact(&mut device_data)?;
+                PartialResource::DeviceSpecific(device_data)
+            }
+            Some(ResourceType::BusNumber) => PartialResource::BusNumber {
+                start: data.read_le()?,
+                length: data.read_le()?,
+            },
+            _ => PartialResource::Unsupported(union_data.clone()),
+        };
+
+        Ok(Self {
+            raw_resource_type,
+            raw_share_disposition,
+            flags,
+            resource,
+        })
+    }
+
+    /// Returns the type of this resource, or [`None`] if the type is unknown
+    pub fn resource_type(&self) -> Option<ResourceType> {
+        ResourceType::from_u8(self.raw_resource_type)
+    }
+
+    /// Returns the type of this resource as it is stored in the hive
+    pub fn raw_resource_type(&self) -> u8 {
+        self.raw_resource_type
+    }
+
+    /// Returns if this resource can be shared, or [`None`] if the value is unknown
+    pub fn share_disposition(&self) -> Option<ShareDisposition> {
+        ShareDisposition::from_u8(self.raw_share_disposition)
+    }
+
+    /// Returns the type specific flags of this resource
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
+    /// Returns the type specific data of this resource
+    pub fn resource(&self) -> &PartialResource {
+        &self.resource
+    }
+}
+
+impl Display for PartialResourceDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.resource {
+            PartialResource::Port { start, length } => {
+                write!(f, "Port 0x{start:x} (length 0x{length:x})")?
+            }
+            PartialResource::Interrupt {
+                level,
+                vector,
+                affinity,
+            } => write!(
+                f,
+                "Interrupt level {level}, vector {vector}, affinity 0x{affinity:x}"
+            )?,
+            PartialResource::Memory { start, length } => {
+                write!(f, "Memory 0x{start:x} (length 0x{length:x})")?
+            }
+            PartialResource::Dma { channel, port } => {
+                write!(f, "DMA channel {channel}, port {port}")?
+            }
+            PartialResource::DeviceSpecific(data) => write!(f, "DeviceSpecific {data:?}")?,
+            PartialResource::BusNumber { start, length } => {
+                write!(f, "BusNumber {start} (length {length})")?
+            }
+            PartialResource::Unsupported(data) => {
+                write!(f, "ResourceType({}) {data:?}", self.raw_resource_type)?
+            }
+        }
+        if let Some(ShareDisposition::Shared) = self.share_disposition() {
+            write!(f, " shared")?;
+        }
+        Ok(())
+    }
+}
+
+/// represents an `IO_RESOURCE_REQUIREMENTS_LIST`, which is stored as `REG_RESOURCE_REQUIREMENTS_LIST`
+#[derive(Debug, Clone)]
+pub struct ResourceRequirementsList {
+    raw_interface_type: i32,
+    bus_number: u32,
+    slot_number: u32,
+    alternative_lists: Vec<IoResourceList>,
+}
+
+impl ResourceRequirementsList {
+    /// parses the data of a `REG_RESOURCE_REQUIREMENTS_LIST` value
+    pub fn parse(raw_value: &[u8]) -> Result<Self> {
+        let mut reader = Cursor::new(raw_value);
+        let _list_size: u32 = reader.read_le()?;
+        let raw_interface_type = reader.read_le()?;
+        let bus_number = reader.read_le()?;
+        let slot_number = reader.read_le()?;
+        let _reserved: [u32; 3] = reader.read_le()?;
+        let count: u32 = reader.read_le()?;
+        let alternative_lists = (0..count)
+            .map(|_| IoResourceList::read(&mut reader))
+            .collect::<BinResult<Vec<_>>>()?;
+        Ok(Self {
+            raw_interface_type,
+            bus_number,
+            slot_number,
+            alternative_lists,
+        })
+    }
+
+    /// Returns the bus type, or [`None`] if the bus type is unknown
+    pub fn interface_type(&self) -> Option<InterfaceType> {
+        InterfaceType::from_i32(self.raw_interface_type)
+    }
+
+    /// Returns the bus type as it is stored in the hive
+    pub fn raw_interface_type(&self) -> i32 {
+        self.raw_interface_type
+    }
+
+    /// Returns the system-assigned number of the bus
+    pub fn bus_number(&self) -> u32 {
+        self.bus_number
+    }
+
+    /// Returns the slot number of the device on its bus
+    pub fn slot_number(&self) -> u32 {
+        self.slot_number
+    }
+
+    /// Returns the alternative sets of resources the device can work with
+    pub fn alternative_lists(&self) -> &Vec<IoResourceList> {
+        &self.alternative_lists
+    }
+}
+
+impl Display for ResourceRequirementsList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} bus {}, slot {}:",
+            fmt_interface_type(self.raw_interface_type),
+            self.bus_number,
+            self.slot_number
+        )?;
+        for (idx, list) in self.alternative_lists.iter().enumerate() {
+            if idx > 0 {
+                write!(f, " or")?;
+            }
+            write!(f, " {list}")?;
+        }
+        Ok(())
+    }
+}
+
+/// represents an `IO_RESOURCE_LIST`
+#[derive(Debug, Clone)]
+pub struct IoResourceList {
+    version: u16,
+    revision: u16,
+    descriptors: Vec<IoResourceDescriptor>,
+}
+
+impl IoResourceList {
+    fn read<R: Read + Seek>(reader: &mut R) -> BinResult<Self> {
+        let version = reader.read_le()?;
+        let revision = reader.read_le()?;
+        let count: u32 = reader.read_le()?;
+        let descriptors = (0..count)
+            .map(|_| IoResourceDescriptor::read(reader))
+            .collect::<BinResult<Vec<_>>>()?;
+        Ok(Self {
+            version,
+            revision,
+            descriptors,
+        })
+    }
+
+    /// Returns the version of this list
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Returns the revision of this list
+    pub fn revision(&self) -> u16 {
+        self.revision
+    }
+
+    /// Returns the required resources
+    pub fn descriptors(&self) -> &Vec<IoResourceDescriptor> {
+        &self.descriptors
+    }
+}
+
+impl Display for IoResourceList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+        for (idx, descriptor) in self.descriptors.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{descriptor}")?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// represents an `IO_RESOURCE_DESCRIPTOR`
+#[derive(Debug, Clone)]
+pub struct IoResourceDescriptor {
+    option: u8,
+    raw_resource_type: u8,
+    raw_share_disposition: u8,
+    flags: u16,
+    requirement: IoResource,
+}
+
+/// the type specific part of an [IoResourceDescriptor]
+#[derive(Debug, Clone)]
+pub enum IoResource {
+    Port {
+        length: u32,
+        alignment: u32,
+        minimum_address: u64,
+        maximum_address: u64,
+    },
+    Interrupt {
+        minimum_vector: u32,
+        maximum_vector: u32,
+    },
+    Memory {
+        length: u32,
+        alignment: u32,
+        minimum_address: u64,
+        maximum_address: u64,
+    },
+    Dma {
+        minimum_channel: u32,
+        maximum_channel: u32,
+    },
+    BusNumber {
+        length: u32,
+        minimum_bus_number: u32,
+        maximum_bus_number: u32,
+    },
+
+    /// resource whose type is not (yet) supported, containing its raw data
+    Unsupported(Vec<u8>),
+}
+
+impl IoResourceDescriptor {
+    const UNION_SIZE: usize = 24;
+
+    fn read<R: Read + Seek>(reader: &mut R) -> BinResult<Self> {
+        let option = reader.read_le()?;
+        let raw_resource_type: u8 = reader.read_le()?;
+        let raw_share_disposition = reader.read_le()?;
+        let _spare1: u8 = reader.read_le()?;
+        let flags = reader.read_le()?;
+        let _spare2: u16 = reader.read_le()?;
+
+        let mut union_data = vec![0; Self::UNION_SIZE];
+        reader.read_exact(&mut union_data)?;
+        let mut data = Cursor::new(&union_data[..]);
+
+        let requirement = match ResourceType::from_u8(raw_resource_type) {
+            Some(ResourceType::Port) => IoResource::Port {
+                length: data.read_le()?,
+                alignment: data.read_le()?,
+                minimum_address: data.read_le()?,
+                maximum_address: data.read_le()?,
+            },
+            Some(ResourceType::Memory) => IoResource::Memory {
+                length: data.read_le()?,
+                alignment: data.read_le()?,
+                minimum_address: data.read_le()?,
+                maximum_address: data.read_le()?,
+            },
+            Some(ResourceType::Interrupt) => IoResource::Interrupt {
+                minimum_vector: data.read_le()?,
+                maximum_vector: data.read_le()?,
+            },
+            Some(ResourceType::Dma) => IoResource::Dma {
+                minimum_channel: data.read_le()?,
+                maximum_channel: data.read_le()?,
+            },
+            Some(ResourceType::BusNumber) => IoResource::BusNumber {
+                length: data.read_le()?,
+                minimum_bus_number: data.read_le()?,
+                maximum_bus_number: data.read_le()?,
+            },
+            _ => IoResource::Unsupported(union_data.clone()),
+        };
+
+        Ok(Self {
+            option,
+            raw_resource_type,
+            raw_share_disposition,
+            flags,
+            requirement,
+        })
+    }
+
+    /// Returns the `IO_RESOURCE_*` option flags (preferred, alternative, ...) of this requirement
+    pub fn option(&self) -> u8 {
+        self.option
+    }
+
+    /// Returns the type of this resource, or [`None`] if the type is unknown
+    pub fn resource_type(&self) -> Option<ResourceType> {
+        ResourceType::from_u8(self.raw_resource_type)
+    }
+
+    /// Returns the type of this resource as it is stored in the hive
+    pub fn raw_resource_type(&self) -> u8 {
+        self.raw_resource_type
+    }
+
+    /// Returns if this resource can be shared, or [`None`] if the value is unknown
+    pub fn share_disposition(&self) -> Option<ShareDisposition> {
+        ShareDisposition::from_u8(self.raw_share_disposition)
+    }
+
+    /// Returns the type specific flags of this resource
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
+    /// Returns the type specific data of this requirement
+    pub fn requirement(&self) -> &IoResource {
+        &self.requirement
+    }
+}
+
+impl Display for IoResourceDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.requirement {
+            IoResource::Port {
+                length,
+                alignment,
+                minimum_address,
+                maximum_address,
+            } => write!(
+                f,
+                "Port 0x{minimum_address:x}-0x{maximum_address:x} (length 0x{length:x}, alignment 0x{alignment:x})"
+            ),
+            IoResource::Interrupt {
+                minimum_vector,
+                maximum_vector,
+            } => write!(f, "Interrupt {minimum_vector}-{maximum_vector}"),
+            IoResource::Memory {
+                length,
+                alignment,
+                minimum_address,
+                maximum_address,
+            } => write!(
+                f,
+                "Memory 0x{minimum_address:x}-0x{maximum_address:x} (length 0x{length:x}, alignment 0x{alignment:x})"
+            ),
+            IoResource::Dma {
+                minimum_channel,
+                maximum_channel,
+            } => write!(f, "DMA channel {minimum_channel}-{maximum_channel}"),
+            IoResource::BusNumber {
+                length,
+                minimum_bus_number,
+                maximum_bus_number,
+            } => write!(
+                f,
+                "BusNumber {minimum_bus_number}-{maximum_bus_number} (length {length})"
+            ),
+            IoResource::Unsupported(data) => {
+                write!(f, "ResourceType({}) {data:?}", self.raw_resource_type)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_list_layouts() {
+        let mut x86 = Vec::new();
+        x86.extend(1u32.to_le_bytes()); // count
+        x86.extend(1i32.to_le_bytes()); // Isa
+        x86.extend(0u32.to_le_bytes()); // bus number
+        x86.extend(1u16.to_le_bytes()); // version
+        x86.extend(1u16.to_le_bytes()); // revision
+        x86.extend(2u32.to_le_bytes()); // count
+        x86.extend([1, 1, 0x11, 0]); // port
+        x86.extend(0x3f8u64.to_le_bytes());
+        x86.extend(8u32.to_le_bytes());
+        x86.extend([2, 1, 0, 0]); // interrupt
+        x86.extend(4u32.to_le_bytes());
+        x86.extend(4u32.to_le_bytes());
+        x86.extend(1u32.to_le_bytes());
+
+        let list = ResourceList::parse(&x86).unwrap();
+        let bus = &list.descriptors()[0];
+        assert_eq!(bus.interface_type(), Some(InterfaceType::Isa));
+        assert_eq!(bus.partial_descriptors().len(), 2);
+        assert!(matches!(
+            bus.partial_descriptors()[1].resource(),
+            PartialResource::Interrupt {
+                level: 4,
+                vector: 4,
+                affinity: 1
+            }
+        ));
+        assert_eq!(
+            list.to_string(),
+            "Isa bus 0: [Port 0x3f8 (length 0x8), Interrupt level 4, vector 4, affinity 0x1]"
+        );
+    }
+
+    /// a `CM_FULL_RESOURCE_DESCRIPTOR` of a PCI bus, as written by a 64 bit system
+    fn x64_full_resource_descriptor() -> Vec<u8> {
+        let mut x64 = Vec::new();
+        x64.extend(5i32.to_le_bytes()); // PciBus
+        x64.extend(2u32.to_le_bytes()); // bus number
+        x64.extend(1u16.to_le_bytes()); // version
+        x64.extend(1u16.to_le_bytes()); // revision
+        x64.extend(3u32.to_le_bytes()); // count
+        x64.extend([2, 3, 0, 0]); // interrupt
+        x64.extend(16u32.to_le_bytes());
+        x64.extend(17u32.to_le_bytes());
+        x64.extend(0xffff_0000_0000u64.to_le_bytes());
+        x64.extend([3, 1, 0, 0]); // memory
+        x64.extend(0xfe00_0000u64.to_le_bytes());
+        x64.extend(0x1000u32.to_le_bytes());
+        x64.extend([0; 4]);
+        x64.extend([5, 0, 0, 0]); // device specific
+        x64.extend(3u32.to_le_bytes());
+        x64.extend([0; 12]);
+        x64.extend([0xaa, 0xbb, 0xcc]);
+        x64
+    }
+
+    #[test]
+    fn test_full_resource_descriptor_x64() {
+        let raw = x64_full_resource_descriptor();
+        let bus = FullResourceDescriptor::parse(&raw).unwrap();
+        assert_eq!(bus.interface_type(), Some(InterfaceType::PciBus));
+        assert_eq!(bus.bus_number(), 2);
+        assert_eq!(bus.partial_descriptors().len(), 3);
+        assert!(matches!(
+            bus.partial_descriptors()[0].resource(),
+            PartialResource::Interrupt {
+                level: 16,
+                vector: 17,
+                affinity: 0xffff_0000_0000
+            }
+        ));
+        assert_eq!(
+            bus.partial_descriptors()[0].share_disposition(),
+            Some(ShareDisposition::Shared)
+        );
+        assert!(matches!(
+            bus.partial_descriptors()[1].resource(),
+            PartialResource::Memory {
+                start: 0xfe00_0000,
+                length: 0x1000
+            }
+        ));
+        assert!(matches!(
+            bus.partial_descriptors()[2].resource(),
+            PartialResource::DeviceSpecific(data) if data == &[0xaa, 0xbb, 0xcc]
+        ));
+
+        let mut list = 1u32.to_le_bytes().to_vec();
+        list.extend(&raw);
+        let list = ResourceList::parse(&list).unwrap();
+        assert_eq!(list.descriptors().len(), 1);
+        assert_eq!(list.to_string(), bus.to_string());
+    }
+
+    #[test]
+    fn test_oversized_device_specific_data() {
+        let mut raw = x64_full_resource_descriptor();
+
+        // the size of the device specific data is stored right after its descriptor header
+        let size_offset = raw.len() - 3 - 16;
+        raw[size_offset..size_offset + 4].copy_from_slice(&0xffff_fff0u32.to_le_bytes());
+        let error = FullResourceDescriptor::parse(&raw).unwrap_err();
+        assert!(error.to_string().contains("device specific data"));
+
+        let mut list = 1u32.to_le_bytes().to_vec();
+        list.extend(&raw);
+        assert!(ResourceList::parse(&list).is_err());
+    }
+
+    #[test]
+    fn test_resource_requirements_list() {
+        let mut raw = Vec::new();
+        raw.extend(0u32.to_le_bytes()); // list size
+        raw.extend(5i32.to_le_bytes()); // PciBus
+        raw.extend(0u32.to_le_bytes()); // bus number
+        raw.extend(3u32.to_le_bytes()); // slot number
+        raw.extend([0; 12]); // reserved
+        raw.extend(2u32.to_le_bytes()); // count of alternative lists
+        for min_vector in [5u32, 7] {
+            raw.extend(1u16.to_le_bytes()); // version
+            raw.extend(1u16.to_le_bytes()); // revision
+            raw.extend(2u32.to_le_bytes()); // count
+            raw.extend([0, 1, 1, 0]); // port
+            raw.extend(0u16.to_le_bytes());
+            raw.extend(0u16.to_le_bytes());
+            raw.extend(8u32.to_le_bytes());
+            raw.extend(1u32.to_le_bytes());
+            raw.extend(0x2f8u64.to_le_bytes());
+            raw.extend(0x3ffu64.to_le_bytes());
+            raw.extend([8, 2, 1, 0]); // alternative interrupt
+            raw.extend(0u16.to_le_bytes());
+            raw.extend(0u16.to_le_bytes());
+            raw.extend(min_vector.to_le_bytes());
+            raw.extend(15u32.to_le_bytes());
+            raw.extend([0; 16]);
+        }
+
+        let list = ResourceRequirementsList::parse(&raw).unwrap();
+        assert_eq!(list.interface_type(), Some(InterfaceType::PciBus));
+        assert_eq!(list.slot_number(), 3);
+        assert_eq!(list.alternative_lists().len(), 2);
+
+        let second = &list.alternative_lists()[1];
+        assert_eq!(second.descriptors().len(), 2);
+        assert_eq!(second.descriptors()[1].option(), 8);
+        assert!(matches!(
+            second.descriptors()[1].requirement(),
+            IoResource::Interrupt {
+                minimum_vector: 7,
+                maximum_vector: 15
+            }
+        ));
+        assert_eq!(
+            list.to_string(),
+            "PciBus bus 0, slot 3: [Port 0x2f8-0x3ff (length 0x8, alignment 0x1), Interrupt 5-15] \
+             or [Port 0x2f8-0x3ff (length 0x8, alignment 0x1), Interrupt 7-15]"
+        );
+
+        // an alternative list which is missing must not be ignored
+        raw.truncate(raw.len() - 40);
+        assert!(ResourceRequirementsList::parse(&raw).is_err());
+    }
+}
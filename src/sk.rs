@@ -0,0 +1,524 @@
+use std::fmt::Display;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use binread::{derive_binread, BinRead, BinReaderExt, BinResult, ReadOptions};
+use bitflags::bitflags;
+use winstructs::guid::Guid;
+
+use crate::{Cell, CellHeader, Error, Offset};
+
+#[derive(BinRead)]
+#[br(magic = b"sk")]
+pub struct KeySecurityWithMagic(KeySecurity);
+
+/// represents a key security item (as documented in <https://github.com/msuhanov/regf/blob/master/Windows%20registry%20file%20format%20specification.md#key-security>)
+///
+/// All key security items of a hive form a doubly linked list, and every item is
+/// shared between all key nodes which have the same security descriptor.
+#[derive_binread]
+#[derive(Debug)]
+pub struct KeySecurity {
+    #[br(temp, parse_with=current_position)]
+    start: u64,
+
+    #[br(temp)]
+    reserved: u16,
+
+    flink: Offset,
+    blink: Offset,
+    reference_count: u32,
+
+    #[br(temp)]
+    security_descriptor_size: u32,
+
+    #[br(parse_with=parse_security_descriptor, args(start, security_descriptor_size))]
+    security_descriptor: SecurityDescriptor,
+}
+
+impl KeySecurity {
+    /// Returns the offset of the next key security item
+    pub fn flink(&self) -> Offset {
+        self.flink
+    }
+
+    /// Returns the offset of the previous key security item
+    pub fn blink(&self) -> Offset {
+        self.blink
+    }
+
+    /// Returns the number of key nodes which reference this key security item
+    pub fn reference_count(&self) -> u32 {
+        self.reference_count
+    }
+
+    /// Returns the security descriptor stored in this key security item
+    pub fn security_descriptor(&self) -> &SecurityDescriptor {
+        &self.security_descriptor
+    }
+}
+
+fn current_position<R: Read + Seek>(reader: &mut R, _ro: &ReadOptions, _: ()) -> BinResult<u64> {
+    Ok(reader.stream_position()?)
+}
+
+fn parse_security_descriptor<R: Read + Seek>(
+    reader: &mut R,
+    _ro: &ReadOptions,
+    args: (u64, u32),
+) -> BinResult<SecurityDescriptor> {
+    let (start, security_descriptor_size) = args;
+
+    // don't trust the size before we know that the cell is large enough. A key security
+    // item is always preceded by the cell header and the `sk` magic number
+    let pos = reader.stream_position()?;
+    let cell_offset = start.saturating_sub(6);
+    reader.seek(SeekFrom::Start(cell_offset))?;
+    let header: CellHeader = reader.read_le()?;
+    reader.seek(SeekFrom::Start(pos))?;
+
+    let expected = (pos - cell_offset) as usize - 4 + security_descriptor_size as usize;
+    if expected > header.contents_size() {
+        return Err(Error::CellTooSmall {
+            offset: Offset(cell_offset as u32),
+            expected,
+            found: header.contents_size(),
+        }
+        .into_binread_error(pos));
+    }
+
+    // all offsets inside of a self-relative security descriptor are relative
+    // to its beginning, so we parse it from a separate buffer
+    let mut buffer = vec![0; security_descriptor_size as usize];
+    reader.read_exact(&mut buffer)?;
+    Cursor::new(buffer).read_le()
+}
+
+bitflags! {
+    /// control flags of a [SecurityDescriptor]
+    pub struct SecurityDescriptorControl: u16 {
+        const SE_OWNER_DEFAULTED = 0x0001;
+        const SE_GROUP_DEFAULTED = 0x0002;
+        const SE_DACL_PRESENT = 0x0004;
+        const SE_DACL_DEFAULTED = 0x0008;
+        const SE_SACL_PRESENT = 0x0010;
+        const SE_SACL_DEFAULTED = 0x0020;
+        const SE_DACL_AUTO_INHERIT_REQ = 0x0100;
+        const SE_SACL_AUTO_INHERIT_REQ = 0x0200;
+        const SE_DACL_AUTO_INHERITED = 0x0400;
+        const SE_SACL_AUTO_INHERITED = 0x0800;
+        const SE_DACL_PROTECTED = 0x1000;
+        const SE_SACL_PROTECTED = 0x2000;
+        const SE_RM_CONTROL_VALID = 0x4000;
+        const SE_SELF_RELATIVE = 0x8000;
+    }
+}
+
+/// represents a self-relative security descriptor
+/// (<https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-security_descriptor_relative>)
+#[derive_binread]
+#[derive(Debug)]
+pub struct SecurityDescriptor {
+    revision: u8,
+
+    #[br(temp)]
+    sbz1: u8,
+
+    #[br(map=SecurityDescriptorControl::from_bits_truncate)]
+    control: SecurityDescriptorControl,
+
+    #[br(temp)]
+    owner_offset: u32,
+
+    #[br(temp)]
+    group_offset: u32,
+
+    #[br(temp)]
+    sacl_offset: u32,
+
+    #[br(temp)]
+    dacl_offset: u32,
+
+    #[br(   if(owner_offset != 0),
+            seek_before(std::io::SeekFrom::Start(owner_offset.into())),
+            restore_position)]
+    owner: Option<Sid>,
+
+    #[br(   if(group_offset != 0),
+            seek_before(std::io::SeekFrom::Start(group_offset.into())),
+            restore_position)]
+    group: Option<Sid>,
+
+    #[br(   if(control.contains(SecurityDescriptorControl::SE_SACL_PRESENT) && sacl_offset != 0),
+            seek_before(std::io::SeekFrom::Start(sacl_offset.into())),
+            restore_position)]
+    sacl: Option<Acl>,
+
+    #[br(   if(control.contains(SecurityDescriptorControl::SE_DACL_PRESENT) && dacl_offset != 0),
+            seek_before(std::io::SeekFrom::Start(dacl_offset.into())),
+            restore_position)]
+    dacl: Option<Acl>,
+}
+
+impl SecurityDescriptor {
+    /// Returns the revision of this security descriptor
+    pub fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    /// Returns the control flags of this security descriptor
+    pub fn control(&self) -> SecurityDescriptorControl {
+        self.control
+    }
+
+    /// Returns the owner of the key, if the security descriptor contains one
+    pub fn owner(&self) -> Option<&Sid> {
+        self.owner.as_ref()
+    }
+
+    /// Returns the primary group of the key, if the security descriptor contains one
+    pub fn group(&self) -> Option<&Sid> {
+        self.group.as_ref()
+    }
+
+    /// Returns the system access control list, if there is one
+    pub fn sacl(&self) -> Option<&Acl> {
+        self.sacl.as_ref()
+    }
+
+    /// Returns the discretionary access control list, if there is one.
+    ///
+    /// Note that a missing DACL grants full access to everyone, while an empty DACL
+    /// grants no access at all.
+    pub fn dacl(&self) -> Option<&Acl> {
+        self.dacl.as_ref()
+    }
+}
+
+/// represents a security identifier (<https://learn.microsoft.com/en-us/windows/win32/secauthz/security-identifiers>)
+#[derive_binread]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Sid {
+    revision: u8,
+
+    #[br(temp)]
+    sub_authority_count: u8,
+
+    #[br(map=|raw: [u8; 6]| raw.iter().fold(0, |acc, b| (acc << 8) | u64::from(*b)))]
+    identifier_authority: u64,
+
+    #[br(count=sub_authority_count)]
+    sub_authorities: Vec<u32>,
+}
+
+impl Sid {
+    /// Returns the revision of this SID, which should always be `1`
+    pub fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    /// Returns the 48bit identifier authority
+    pub fn identifier_authority(&self) -> u64 {
+        self.identifier_authority
+    }
+
+    /// Returns the list of subauthorities; the last one is the relative identifier (RID)
+    pub fn sub_authorities(&self) -> &[u32] {
+        &self.sub_authorities[..]
+    }
+}
+
+impl Display for Sid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "S-{}-", self.revision)?;
+
+        // <https://learn.microsoft.com/en-us/windows/win32/api/sddl/nf-sddl-convertsidtostringsida>
+        if self.identifier_authority >= 1 << 32 {
+            write!(f, "0x{:012X}", self.identifier_authority)?;
+        } else {
+            write!(f, "{}", self.identifier_authority)?;
+        }
+
+        for sub_authority in self.sub_authorities.iter() {
+            write!(f, "-{sub_authority}")?;
+        }
+        Ok(())
+    }
+}
+
+/// represents an access control list (<https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-acl>)
+#[derive_binread]
+#[derive(Debug)]
+pub struct Acl {
+    revision: u8,
+
+    #[br(temp)]
+    sbz1: u8,
+
+    #[br(temp)]
+    acl_size: u16,
+
+    #[br(temp)]
+    ace_count: u16,
+
+    #[br(temp)]
+    sbz2: u16,
+
+    #[br(count=ace_count)]
+    aces: Vec<Ace>,
+}
+
+impl Acl {
+    /// Returns the revision of this ACL
+    pub fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    /// Returns the access control entries of this ACL, in the order in which they are stored
+    pub fn aces(&self) -> &Vec<Ace> {
+        &self.aces
+    }
+}
+
+/// Types of access control entries, as listed in
+/// <https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-ace_header>
+#[derive(Debug, Clone, Copy, Eq, PartialEq, num_derive::FromPrimitive)]
+pub enum AceType {
+    AccessAllowed = 0x00,
+    AccessDenied = 0x01,
+    SystemAudit = 0x02,
+    SystemAlarm = 0x03,
+    AccessAllowedCompound = 0x04,
+    AccessAllowedObject = 0x05,
+    AccessDeniedObject = 0x06,
+    SystemAuditObject = 0x07,
+    SystemAlarmObject = 0x08,
+    AccessAllowedCallback = 0x09,
+    AccessDeniedCallback = 0x0a,
+    AccessAllowedCallbackObject = 0x0b,
+    AccessDeniedCallbackObject = 0x0c,
+    SystemAuditCallback = 0x0d,
+    SystemAlarmCallback = 0x0e,
+    SystemAuditCallbackObject = 0x0f,
+    SystemAlarmCallbackObject = 0x10,
+    SystemMandatoryLabel = 0x11,
+    SystemResourceAttribute = 0x12,
+    SystemScopedPolicyId = 0x13,
+    SystemProcessTrustLabel = 0x14,
+    SystemAccessFilter = 0x15,
+}
+
+impl AceType {
+    fn has_object_types(&self) -> bool {
+        matches!(
+            self,
+            Self::AccessAllowedObject
+                | Self::AccessDeniedObject
+                | Self::SystemAuditObject
+                | Self::SystemAlarmObject
+                | Self::AccessAllowedCallbackObject
+                | Self::AccessDeniedCallbackObject
+                | Self::SystemAuditCallbackObject
+                | Self::SystemAlarmCallbackObject
+        )
+    }
+}
+
+bitflags! {
+    /// inheritance and audit flags of an [Ace]
+    pub struct AceFlags: u8 {
+        const OBJECT_INHERIT_ACE = 0x01;
+        const CONTAINER_INHERIT_ACE = 0x02;
+        const NO_PROPAGATE_INHERIT_ACE = 0x04;
+        const INHERIT_ONLY_ACE = 0x08;
+        const INHERITED_ACE = 0x10;
+        const SUCCESSFUL_ACCESS_ACE_FLAG = 0x40;
+        const FAILED_ACCESS_ACE_FLAG = 0x80;
+    }
+}
+
+bitflags! {
+    /// access rights for registry keys
+    /// (<https://learn.microsoft.com/en-us/windows/win32/sysinfo/registry-key-security-and-access-rights>)
+    pub struct KeyAccessRights: u32 {
+        const KEY_QUERY_VALUE = 0x0000_0001;
+        const KEY_SET_VALUE = 0x0000_0002;
+        const KEY_CREATE_SUB_KEY = 0x0000_0004;
+        const KEY_ENUMERATE_SUB_KEYS = 0x0000_0008;
+        const KEY_NOTIFY = 0x0000_0010;
+        const KEY_CREATE_LINK = 0x0000_0020;
+        const KEY_WOW64_64KEY = 0x0000_0100;
+        const KEY_WOW64_32KEY = 0x0000_0200;
+        const DELETE = 0x0001_0000;
+        const READ_CONTROL = 0x0002_0000;
+        const WRITE_DAC = 0x0004_0000;
+        const WRITE_OWNER = 0x0008_0000;
+        const ACCESS_SYSTEM_SECURITY = 0x0100_0000;
+        const MAXIMUM_ALLOWED = 0x0200_0000;
+        const GENERIC_ALL = 0x1000_0000;
+        const GENERIC_EXECUTE = 0x2000_0000;
+        const GENERIC_WRITE = 0x4000_0000;
+        const GENERIC_READ = 0x8000_0000;
+    }
+}
+
+/// represents a single access control entry
+#[derive_binread]
+#[derive(Debug)]
+pub struct Ace {
+    raw_ace_type: u8,
+
+    #[br(map=AceFlags::from_bits_truncate)]
+    flags: AceFlags,
+
+    #[br(temp, assert(ace_size >= 4, "invalid ACE size: {}", ace_size))]
+    ace_size: u16,
+
+    #[br(parse_with=parse_ace_data, args(raw_ace_type, ace_size - 4))]
+    data: AceData,
+}
+
+/// the type specific part of an [Ace]
+#[derive(Debug)]
+pub enum AceData {
+    /// ACE which grants or denies some access rights to a trustee
+    Basic { access_mask: u32, sid: Sid },
+
+    /// ACE which only applies to a certain type of object or property
+    Object {
+        access_mask: u32,
+        object_type: Option<Guid>,
+        inherited_object_type: Option<Guid>,
+        sid: Sid,
+    },
+
+    /// ACE whose type is not (yet) supported, containing its raw data
+    Unsupported(Vec<u8>),
+}
+
+fn parse_ace_data<R: Read + Seek>(
+    reader: &mut R,
+    _ro: &ReadOptions,
+    args: (u8, u16),
+) -> BinResult<AceData> {
+    let (raw_ace_type, data_size) = args;
+    let mut buffer = vec![0; data_size.into()];
+    reader.read_exact(&mut buffer)?;
+
+    let mut cursor = Cursor::new(buffer);
+    let ace_data = match num_traits::FromPrimitive::from_u8(raw_ace_type) {
+        None | Some(AceType::AccessAllowedCompound) => AceData::Unsupported(cursor.into_inner()),
+        Some(ace_type) => {
+            if AceType::has_object_types(&ace_type) {
+                const ACE_OBJECT_TYPE_PRESENT: u32 = 0x1;
+                const ACE_INHERITED_OBJECT_TYPE_PRESENT: u32 = 0x2;
+
+                let access_mask = cursor.read_le()?;
+                let flags: u32 = cursor.read_le()?;
+                let mut read_guid = |present: bool| -> BinResult<Option<Guid>> {
+                    if present {
+                        Guid::from_reader(&mut cursor).map(Some).map_err(|why| {
+                            binread::Error::Custom {
+                                pos: 0,
+                                err: Box::new(why.to_string()),
+                            }
+                        })
+                    } else {
+                        Ok(None)
+                    }
+                };
+                let object_type = read_guid(flags & ACE_OBJECT_TYPE_PRESENT != 0)?;
+                let inherited_object_type =
+                    read_guid(flags & ACE_INHERITED_OBJECT_TYPE_PRESENT != 0)?;
+                AceData::Object {
+                    access_mask,
+                    object_type,
+                    inherited_object_type,
+                    sid: cursor.read_le()?,
+                }
+            } else {
+                AceData::Basic {
+                    access_mask: cursor.read_le()?,
+                    sid: cursor.read_le()?,
+                }
+            }
+        }
+    };
+    Ok(ace_data)
+}
+
+impl Ace {
+    /// Returns the type of this ACE, or [`None`] if the type is unknown
+    pub fn ace_type(&self) -> Option<AceType> {
+        num_traits::FromPrimitive::from_u8(self.raw_ace_type)
+    }
+
+    /// Returns the type of this ACE as it is stored in the hive
+    pub fn raw_ace_type(&self) -> u8 {
+        self.raw_ace_type
+    }
+
+    /// Returns the inheritance and audit flags of this ACE
+    pub fn flags(&self) -> AceFlags {
+        self.flags
+    }
+
+    /// Returns the type specific data of this ACE
+    pub fn data(&self) -> &AceData {
+        &self.data
+    }
+
+    /// Returns the trustee of this ACE, if its type is supported
+    pub fn sid(&self) -> Option<&Sid> {
+        match &self.data {
+            AceData::Basic { sid, .. } => Some(sid),
+            AceData::Object { sid, .. } => Some(sid),
+            AceData::Unsupported(_) => None,
+        }
+    }
+
+    /// Returns the access rights granted, denied or audited by this ACE, if its type is supported
+    pub fn access_rights(&self) -> Option<KeyAccessRights> {
+        match &self.data {
+            AceData::Basic { access_mask, .. } => {
+                Some(KeyAccessRights::from_bits_truncate(*access_mask))
+            }
+            AceData::Object { access_mask, .. } => {
+                Some(KeyAccessRights::from_bits_truncate(*access_mask))
+            }
+            AceData::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<Cell<KeySecurityWithMagic, ()>> for KeySecurityWithMagic {
+    fn from(cell: Cell<KeySecurityWithMagic, ()>) -> Self {
+        cell.into_data()
+    }
+}
+
+impl From<KeySecurityWithMagic> for KeySecurity {
+    fn from(ksm: KeySecurityWithMagic) -> Self {
+        ksm.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sid_display() {
+        let raw: &[u8] = &[
+            0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x20, 0x00, 0x00, 0x00, 0x20, 0x02,
+            0x00, 0x00,
+        ];
+        let sid: Sid = Cursor::new(raw).read_le().unwrap();
+        assert_eq!(sid.to_string(), "S-1-5-32-544");
+
+        let raw: &[u8] = &[
+            0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let sid: Sid = Cursor::new(raw).read_le().unwrap();
+        assert_eq!(sid.to_string(), "S-1-1-0");
+    }
+}
@@ -1,6 +1,9 @@
+use std::fmt::Display;
+
 use binread::{derive_binread, BinRead};
 
-use crate::{Offset, Cell};
+use crate::name_comparison::nt_upcase;
+use crate::{Offset, Cell, NameComparison, ParseContext};
 
 /// On-Disk Structure of a Subkeys List header.
 /// This is common for all subkey types (Fast Leaf, Hash Leaf, Index Leaf, Index Root).
@@ -43,7 +46,6 @@ pub(crate) enum SubKeysList {
 }
 
 impl SubKeysList {
-    #[allow(unused)]
     pub fn offsets<'a>(&'a self) -> Box<dyn Iterator<Item=Offset> + 'a> {
         match self {
             SubKeysList::IndexLeaf { items, ..} => Box::new(items.iter().map(|i| i.key_node_offset)),
@@ -62,9 +64,81 @@ impl SubKeysList {
         }
     }
 
+    /// returns the offsets of all key nodes which might have one of the names in `names` (as UTF-16 code
+    /// units). This uses the name hashes of hash leafs and the name hints of fast leafs, so that not all
+    /// key nodes need to be read. For index leafs, all offsets are returned.
+    ///
+    /// `names` contains all forms in which a name might be stored: hashes and hints of compressed names
+    /// are computed over their raw bytes, which differ from the name if a codepage other than Latin-1 is used.
+    pub fn candidate_offsets(&self, names: &[Vec<u16>], comparison: NameComparison) -> Vec<Offset> {
+        // hashes and hints are computed the way Windows compares names. If we use another
+        // comparison, we can use them only for ASCII names
+        if comparison != NameComparison::NtUpcase && names.iter().flatten().any(|c| *c >= 0x80) {
+            return self.offsets().collect();
+        }
+
+        match self {
+            SubKeysList::FastLeaf { items, ..} => {
+                items.iter().filter(|i| names.iter().any(|n| i.matches(n))).map(|i| i.key_node_offset).collect()
+            }
+            SubKeysList::HashLeaf { items, ..} => {
+                let hashes: Vec<u32> = names.iter().map(|n| name_hash(n)).collect();
+                items.iter().filter(|i| hashes.contains(&i.name_hash())).map(|i| i.key_node_offset).collect()
+            }
+            SubKeysList::IndexLeaf { .. } | SubKeysList::IndexRoot { .. } => self.offsets().collect(),
+        }
+    }
+
+    /// checks if the name hash or name hint of the entry with index `index` matches `name`
+    /// (as UTF-16 code units, where every byte of a compressed name is a code unit). Index leafs contain neither hashes nor hints, so there is nothing to check.
+    pub fn check_entry(&self, list_offset: Offset, index: usize, name: &[u16]) -> Option<SubkeysListIssue> {
+        match self {
+            SubKeysList::FastLeaf { items, ..} => {
+                let item = items.get(index)?;
+                (!item.matches(name)).then(|| SubkeysListIssue::WrongHint {
+                    list_offset,
+                    key_node_offset: item.key_node_offset,
+                    name: String::from_utf16_lossy(name),
+                    stored: item.name_hint,
+                })
+            }
+            SubKeysList::HashLeaf { items, ..} => {
+                let item = items.get(index)?;
+                let expected = name_hash(name);
+                (item.name_hash() != expected).then(|| SubkeysListIssue::WrongHash {
+                    list_offset,
+                    key_node_offset: item.key_node_offset,
+                    name: String::from_utf16_lossy(name),
+                    stored: item.name_hash(),
+                    expected,
+                })
+            }
+            SubKeysList::IndexLeaf { .. } | SubKeysList::IndexRoot { .. } => None,
+        }
+    }
+
     pub fn is_index_root(&self) -> bool {
         matches!(self, SubKeysList::IndexRoot { items: _ , ..})
     }
+
+    /// returns [true] if this type of subkeys list is allowed in a hive with the given format version
+    pub fn is_supported_by(&self, ctx: &ParseContext) -> bool {
+        match self {
+            SubKeysList::FastLeaf { .. } => ctx.supports_fast_leaf(),
+            SubKeysList::HashLeaf { .. } => ctx.supports_hash_leaf(),
+            SubKeysList::IndexLeaf { .. } | SubKeysList::IndexRoot { .. } => true,
+        }
+    }
+
+    /// we don't refuse to read subkeys lists which should not exist in a hive of the given version,
+    /// but we want to know about them. [`KeyNode::verify_subkeys_lists`](crate::KeyNode::verify_subkeys_lists)
+    /// reports them as [`SubkeysListIssue::UnsupportedListType`]
+    pub fn warn_if_unsupported(&self, ctx: &ParseContext, offset: Offset) {
+        if ! self.is_supported_by(ctx) {
+            log::warn!("found a subkeys list at offset 0x{:08x} which is not supported by hive format version 1.{}: {:?}",
+                offset.0, ctx.minor_version(), self);
+        }
+    }
 }
 
 impl From<Cell<SubKeysList, ()>> for SubKeysList {
@@ -73,22 +147,152 @@ impl From<Cell<SubKeysList, ()>> for SubKeysList {
     }
 }
 
+/// is returned as [`Error::SubkeysList`](crate::Error::SubkeysList) if the subkeys lists
+/// of a key cannot be traversed
+#[derive(thiserror::Error, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SubkeysListError {
+    /// an index root refers to itself or to one of the index roots which refer to it
+    #[error("the index root at offset 0x{:08x} is part of a cycle of index roots", offset.0)]
+    Cycle { offset: Offset },
+
+    /// there are more nested index roots than allowed by
+    /// [`Hive::max_subkeys_list_depth`](crate::Hive::max_subkeys_list_depth)
+    #[error("the subkeys list at offset 0x{:08x} exceeds the maximum depth of {max_depth}", offset.0)]
+    TooDeep { offset: Offset, max_depth: usize },
+}
+
+/// Describes a violation of the rules which Windows expects subkeys lists to follow.
+/// Windows might not find keys which are referenced by such lists. Use
+/// [`KeyNode::verify_subkeys_lists`](crate::KeyNode::verify_subkeys_lists) to find such issues.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SubkeysListIssue {
+    /// the subkey is not sorted after its predecessor. Subkeys must be sorted by
+    /// their upper case names.
+    OutOfOrder {
+        list_offset: Offset,
+        key_node_offset: Offset,
+        previous_name: String,
+        name: String,
+    },
+
+    /// the name hash stored in a hash leaf does not match the name of the subkey
+    WrongHash {
+        list_offset: Offset,
+        key_node_offset: Offset,
+        name: String,
+        stored: u32,
+        expected: u32,
+    },
+
+    /// the name hint stored in a fast leaf does not match the name of the subkey
+    WrongHint {
+        list_offset: Offset,
+        key_node_offset: Offset,
+        name: String,
+        stored: [u8; 4],
+    },
+
+    /// the number of subkeys found in the subkeys lists differs from the number
+    /// of subkeys stored in the key node
+    WrongCount { declared: u32, found: usize },
+
+    /// the same subkeys list is referenced more than once by the index roots of a key.
+    /// Such lists are read only once.
+    DuplicateList { list_offset: Offset },
+
+    /// the same key node is referenced more than once
+    DuplicateOffset {
+        list_offset: Offset,
+        key_node_offset: Offset,
+    },
+
+    /// there is more than one subkey with the same name
+    DuplicateName {
+        list_offset: Offset,
+        key_node_offset: Offset,
+        name: String,
+    },
+
+    /// the entry does not refer to a valid key node
+    UnreadableSubkey {
+        list_offset: Offset,
+        key_node_offset: Offset,
+        error: String,
+    },
+
+    /// the subkeys list has a type which has been introduced after the format version
+    /// of the hive, such as a hash leaf in a hive of version 1.3. Such lists are read anyway.
+    UnsupportedListType { list_offset: Offset, minor_version: u32 },
+}
+
+impl Display for SubkeysListIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubkeysListIssue::OutOfOrder { list_offset, key_node_offset, previous_name, name } => write!(f,
+                "subkey '{name}' at 0x{:08x} is sorted after '{previous_name}' (list at 0x{:08x})", key_node_offset.0, list_offset.0),
+            SubkeysListIssue::WrongHash { list_offset, key_node_offset, name, stored, expected } => write!(f,
+                "subkey '{name}' at 0x{:08x} has hash 0x{stored:08x}, but expected 0x{expected:08x} (list at 0x{:08x})", key_node_offset.0, list_offset.0),
+            SubkeysListIssue::WrongHint { list_offset, key_node_offset, name, stored } => write!(f,
+                "subkey '{name}' at 0x{:08x} has hint {stored:?} (list at 0x{:08x})", key_node_offset.0, list_offset.0),
+            SubkeysListIssue::WrongCount { declared, found } => write!(f,
+                "the key node declares {declared} subkeys, but {found} subkeys have been found"),
+            SubkeysListIssue::DuplicateList { list_offset } => write!(f,
+                "the subkeys list at 0x{:08x} is referenced more than once", list_offset.0),
+            SubkeysListIssue::DuplicateOffset { list_offset, key_node_offset } => write!(f,
+                "subkey at 0x{:08x} is referenced more than once (list at 0x{:08x})", key_node_offset.0, list_offset.0),
+            SubkeysListIssue::DuplicateName { list_offset, key_node_offset, name } => write!(f,
+                "subkey '{name}' at 0x{:08x} has the same name as another subkey (list at 0x{:08x})", key_node_offset.0, list_offset.0),
+            SubkeysListIssue::UnreadableSubkey { list_offset, key_node_offset, error } => write!(f,
+                "unable to read subkey at 0x{:08x}: {error} (list at 0x{:08x})", key_node_offset.0, list_offset.0),
+            SubkeysListIssue::UnsupportedListType { list_offset, minor_version } => write!(f,
+                "the subkeys list at 0x{:08x} is not supported by hive format version 1.{minor_version}", list_offset.0),
+        }
+    }
+}
+
+/// computes the hash of a key name (as UTF-16 code units), as it is stored in hash leafs
+/// (<https://github.com/msuhanov/regf/blob/master/Windows%20registry%20file%20format%20specification.md#hash-leaf>)
+pub(crate) fn name_hash(name: &[u16]) -> u32 {
+    name.iter().fold(0u32, |hash, &c| {
+        hash.wrapping_mul(37).wrapping_add(u32::from(nt_upcase(c)))
+    })
+}
+
 #[derive(BinRead, Debug)]
 pub struct HashLeafItem {
     key_node_offset: Offset,
-
-    #[allow(unused)]
     name_hash: [u8; 4],
 }
 
+impl HashLeafItem {
+    pub fn name_hash(&self) -> u32 {
+        u32::from_le_bytes(self.name_hash)
+    }
+}
+
 #[derive(BinRead, Debug)]
 pub struct FastLeafItem {
     key_node_offset: Offset,
-
-    #[allow(unused)]
     name_hint: [u8; 4],
 }
 
+impl FastLeafItem {
+    /// returns [false] if the name hint shows that the key node does not have the
+    /// name `name` (as UTF-16 code units). The name hint consists of the first four characters of
+    /// the name, padded with NUL bytes. Names which contain characters above `0xff`
+    /// in their first four characters have no usable hint.
+    pub fn matches(&self, name: &[u16]) -> bool {
+        let name: Vec<u16> = name.iter().copied().chain(std::iter::repeat(0)).take(4).collect();
+        if name.iter().any(|c| *c > 0xff) || self.name_hint == [0; 4] {
+            return true;
+        }
+        self.name_hint
+            .iter()
+            .zip(name)
+            .all(|(h, n)| nt_upcase(u16::from(*h)) == nt_upcase(n))
+    }
+}
+
 #[derive(BinRead, Debug)]
 pub struct IndexRootListElement {
     subkeys_list_offset: Offset
@@ -97,4 +301,66 @@ pub struct IndexRootListElement {
 #[derive(BinRead, Debug)]
 pub struct IndexLeafItem {
     key_node_offset: Offset,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CleanHive, Hive, HiveParseMode};
+
+    fn utf16(s: &str) -> Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    #[test]
+    fn test_name_hash() {
+        assert_eq!(name_hash(&utf16("")), 0);
+        assert_eq!(name_hash(&utf16("a")), 0x41);
+        assert_eq!(name_hash(&utf16("aB")), 0x41 * 37 + 0x42);
+        assert_eq!(name_hash(&utf16("Software")), name_hash(&utf16("SOFTWARE")));
+    }
+
+    #[test]
+    fn test_name_hint() {
+        let item = FastLeafItem {
+            key_node_offset: Offset(0),
+            name_hint: *b"Soft",
+        };
+        assert!(item.matches(&utf16("Software")));
+        assert!(item.matches(&utf16("SOFT")));
+        assert!(!item.matches(&utf16("System")));
+        assert!(!item.matches(&utf16("Sof")));
+
+        let item = FastLeafItem {
+            key_node_offset: Offset(0),
+            name_hint: *b"ab\0\0",
+        };
+        assert!(item.matches(&utf16("AB")));
+        assert!(!item.matches(&utf16("abc")));
+
+        let item = FastLeafItem {
+            key_node_offset: Offset(0),
+            name_hint: [b'c', 0xe4, b'f', 0],
+        };
+        assert!(item.matches(&utf16("CÄF")));
+        assert!(!item.matches(&utf16("CAF")));
+    }
+
+    #[test]
+    fn test_hashes_of_testhive() {
+        let mut hive = Hive::<_, CleanHive>::new(
+            std::fs::File::open("tests/data/testhive").unwrap(),
+            HiveParseMode::NormalWithBaseBlock,
+        )
+        .unwrap();
+        let root_key = hive.root_key_node().unwrap();
+        let subkeys_list: SubKeysList = hive.read_structure(root_key.subkeys_list_offset()).unwrap();
+        let SubKeysList::HashLeaf { items } = subkeys_list else {
+            panic!("expected a hash leaf");
+        };
+        assert_eq!(items.len(), 5);
+        for item in items {
+            let key_node = hive.key_at(item.key_node_offset).unwrap();
+            assert_eq!(item.name_hash(), name_hash(&key_node.name_utf16()));
+        }
+    }
+}
@@ -16,25 +16,24 @@ pub struct DirtyPagesReference {
 
 impl DirtyPagesReference {
     pub fn contains_offset(&self, offset: Offset) -> bool {
-        self.offset.0 <= offset.0 && offset.0 < self.offset.0 + self.size
+        self.offset.0 <= offset.0 && u64::from(offset.0) < u64::from(self.offset.0) + u64::from(self.size)
     }
 
     pub fn contains(&self, offset: Offset, size: usize) -> bool {
         if size == 0 {
             false
         } else {
-            match TryInto::<u32>::try_into(size) {
-                Ok(size) => {
-                    let last_byte_offset = Offset(offset.0 + size - 1);
-                    self.contains_offset(offset) && self.contains_offset(last_byte_offset)
+            match TryInto::<u32>::try_into(size - 1).ok().and_then(|s| offset.0.checked_add(s)) {
+                Some(last_byte_offset) => {
+                    self.contains_offset(offset) && self.contains_offset(Offset(last_byte_offset))
                 }
-                Err(_) => false,
+                None => false,
             }
         }
     }
 
     pub fn last_byte_offset(&self) -> Offset {
-        Offset(self.offset.0 + self.size - 1)
+        Offset(self.offset.0.saturating_add(self.size).saturating_sub(1))
     }
 }
 
@@ -70,7 +69,8 @@ impl Eq for DirtyPagesReference {
 
 impl Ord for DirtyPagesReference {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        // overlapping pages cannot be ordered by `partial_cmp`
+        self.offset.cmp(&other.offset).then(self.size.cmp(&other.size))
     }
 }
 
@@ -64,18 +64,18 @@ impl From<TransactionLog> for Vec<TransactionLogsEntry> {
 }
 
 impl TryFrom<File> for TransactionLog {
-    type Error = binread::Error;
+    type Error = crate::Error;
 
     fn try_from(mut file: File) -> Result<Self, Self::Error> {
-        file.read_le::<TransactionLog>()
+        Ok(file.read_le::<TransactionLog>()?)
     }
 }
 
 impl TryFrom<&mut File> for TransactionLog {
-    type Error = binread::Error;
+    type Error = crate::Error;
 
     fn try_from(file: &mut File) -> Result<Self, Self::Error> {
-        file.read_le::<TransactionLog>()
+        Ok(file.read_le::<TransactionLog>()?)
     }
 }
 
@@ -3,11 +3,12 @@ use std::{
     io::{Read, Seek},
 };
 
-use binread::{BinRead, BinResult, ReadOptions};
+use binread::{derive_binread, BinRead, BinResult, ReadOptions};
 use derive_getters::Getters;
 use marvin32::Marvin32;
 
 use super::dirty_pages::{DirtyPage, DirtyPagesReference};
+use crate::Error;
 
 pub const BLOCK_SIZE: u32 = 512;
 #[allow(dead_code)]
@@ -43,11 +44,15 @@ impl From<Marvin32Hash> for u32 {
 }
 
 /// <https://github.com/msuhanov/regf/blob/master/Windows%20registry%20file%20format%20specification.md#new-format>
-#[derive(BinRead, Debug, Clone, Default, Getters)]
-#[br(magic = b"HvLE", assert(hash1.collapse() == calc_hash1(&dirty_pages_references, &dirty_pages, &slack), "expected 0x{:08x}", hash1.collapse()))]
+#[derive_binread]
+#[derive(Debug, Clone, Default, Getters)]
+#[br(magic = b"HvLE")]
 pub struct TransactionLogsEntry {
+    #[br(temp, parse_with = parse_entry_offset)]
+    entry_offset: u64,
+
     /// Size of a current log entry in bytes
-    #[br(assert(size > BLOCK_SIZE || size % BLOCK_SIZE == 0))]
+    #[br(assert(size > BLOCK_SIZE || size.is_multiple_of(BLOCK_SIZE)))]
     size: u32,
 
     /// Partial copy of the Flags field of the base block at the time of
@@ -79,7 +84,7 @@ pub struct TransactionLogsEntry {
 
     /// Copy of the Hive bins data size field of the base block at the time of
     /// creation of a current log entry
-    #[br(assert(hbin_data_size > HIVE_BIN_SIZE_ALIGNMENT || hbin_data_size % HIVE_BIN_SIZE_ALIGNMENT == 0))]
+    #[br(assert(hbin_data_size > HIVE_BIN_SIZE_ALIGNMENT || hbin_data_size.is_multiple_of(HIVE_BIN_SIZE_ALIGNMENT)))]
     hbin_data_size: u32,
 
     /// Number of dirty pages attached to a current log entry
@@ -97,7 +102,11 @@ pub struct TransactionLogsEntry {
 
     /// Hash-2 is the Marvin32 hash of the first 32 bytes of a current log
     /// entry (including the Hash-1 calculated before).
-    #[br(assert(hash2.collapse() == calc_hash2(vec![size, flags, sequence_number, hbin_data_size, dirty_pages_count, *hash1.p0(), *hash1.p1()]), "expected 0x{:08x}", hash2.collapse()))]
+    #[br(temp, calc = calc_hash2(vec![size, flags, sequence_number, hbin_data_size, dirty_pages_count, *hash1.p0(), *hash1.p1()]))]
+    expected_hash2: u32,
+
+    #[br(assert(hash2.collapse() == expected_hash2,
+            Error::LogHashMismatch { offset: entry_offset, expected: expected_hash2, found: hash2.collapse() }))]
     hash2: Marvin32Hash,
 
     /// A dirty page reference describes a single page to be written to a
@@ -107,19 +116,25 @@ pub struct TransactionLogsEntry {
     /// |0|4|Offset|Offset of a page in a primary file (in bytes), relative from the start of the hive bins data|
     /// |4|4|Size|Size of a page in bytes|
     #[br(count = dirty_pages_count,
-            assert(dirty_pages_references.len() == dirty_pages_count.try_into().unwrap()))]
+            assert(dirty_pages_references.len() == dirty_pages_count as usize))]
     dirty_pages_references: Vec<DirtyPagesReference>,
 
-    #[br(parse_with = read_dirty_pages, args(&dirty_pages_references[..]),
+    #[br(parse_with = read_dirty_pages, args(&dirty_pages_references[..], size),
             assert(dirty_pages_references.len() == dirty_pages.len()))]
     dirty_pages: Vec<DirtyPage>,
 
-    #[br(calc = ((dirty_pages_references.len() * 8) + dirty_pages.iter().fold(0, |acc, x| acc+x.as_ref().len())).try_into().unwrap())]
+    #[br(calc = ((dirty_pages_references.len() * 8) + dirty_pages.iter().fold(0, |acc, x| acc+x.as_ref().len())).try_into().unwrap_or(u32::MAX),
+            assert(payload_size.checked_add(40).is_some_and(|header_and_payload| header_and_payload <= size), "the payload exceeds the size of the log entry"))]
     payload_size: u32,
 
     /// this is required to calculate the hash-1
     #[br(count = size - (40 + payload_size))]
     slack: Vec<u8>,
+
+    #[br(temp, calc = calc_hash1(&dirty_pages_references, &dirty_pages, &slack),
+            assert(hash1.collapse() == expected_hash1,
+                Error::LogHashMismatch { offset: entry_offset, expected: expected_hash1, found: hash1.collapse() }))]
+    expected_hash1: u32,
 }
 
 impl From<TransactionLogsEntry> for Vec<DirtyPagesReference> {
@@ -134,13 +149,37 @@ impl From<TransactionLogsEntry> for Vec<DirtyPage> {
     }
 }
 
+/// returns the offset of the current log entry, whose magic number has already been read
+fn parse_entry_offset<R: Read + Seek>(
+    reader: &mut R,
+    _ro: &ReadOptions,
+    _: (),
+) -> BinResult<u64> {
+    Ok(reader.stream_position()?.saturating_sub(4))
+}
+
 fn read_dirty_pages<R: Read + Seek>(
     reader: &mut R,
     _ro: &ReadOptions,
-    params: (&[DirtyPagesReference],),
+    params: (&[DirtyPagesReference], u32),
 ) -> BinResult<Vec<DirtyPage>> {
     let mut dirty_pages = Vec::new();
+    let mut remaining = params.1 as usize;
     for dirty_pages_reference in params.0 {
+        // don't trust the page size before we know that the page fits into the log entry
+        remaining = match remaining.checked_sub(*dirty_pages_reference.size() as usize) {
+            Some(remaining) => remaining,
+            None => {
+                return Err(binread::Error::AssertFail {
+                    pos: reader.stream_position()?,
+                    message: format!(
+                        "the dirty page at offset 0x{:08x} exceeds the size of the log entry",
+                        dirty_pages_reference.offset().0
+                    ),
+                })
+            }
+        };
+
         // allocate memory
         let mut data = vec![0; *dirty_pages_reference.size() as usize];
 
@@ -166,7 +205,9 @@ fn calc_hash1(
         hasher.write(page.as_ref());
     }
     hasher.write(slack);
-    hasher.finish().try_into().unwrap()
+
+    // Marvin32 creates 32 bit hashes
+    hasher.finish() as u32
 }
 fn calc_hash2(header_fields: Vec<u32>) -> u32 {
     let mut hasher = Marvin32::new(0x82EF4D887A4E55C5);
@@ -174,5 +215,5 @@ fn calc_hash2(header_fields: Vec<u32>) -> u32 {
     for field in header_fields {
         hasher.write_u32(field);
     }
-    hasher.finish().try_into().unwrap()
+    hasher.finish() as u32
 }
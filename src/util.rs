@@ -1,79 +1,69 @@
 use std::io::{Read, Seek};
 
-use binread::{BinRead, BinReaderExt, BinResult, ReadOptions};
+use binread::{BinReaderExt, BinResult, ReadOptions};
 use chrono::{DateTime, Utc};
-use encoding_rs::{ISO_8859_15, UTF_16LE};
-use winstructs::timestamp::WinTimestamp;
+use encoding_rs::UTF_16LE;
 
-pub(crate) fn parse_string<R: Read + Seek>(
-    reader: &mut R,
+use crate::Codepage;
+
+/// decodes the name of a key or a value, which has already been read as `raw_name`.
+/// If `is_compressed` is set, the name is stored as (extended) ASCII using `codepage`,
+/// otherwise as UTF-16LE.
+pub(crate) fn parse_name<R: Read + Seek>(
+    _reader: &mut R,
     ro: &ReadOptions,
-    params: (bool,),
+    params: (&Vec<u8>, bool, Codepage),
 ) -> BinResult<String> {
-    let raw_string = Vec::<u8>::read_options(reader, ro, ())?;
-
-    let (cow, _, had_errors) = if params.0 {
-        ISO_8859_15.decode(&raw_string[..])
-    } else {
-        UTF_16LE.decode(&raw_string[..])
-    };
+    let (raw_name, is_compressed, codepage) = params;
+    if is_compressed {
+        return Ok(codepage.decode(raw_name));
+    }
 
+    // names which are not valid UTF-16 (e.g. which contain unpaired surrogates) are
+    // accepted by Windows, so we must not fail here. The raw name is kept anyway.
+    let (cow, had_errors) = UTF_16LE.decode_without_bom_handling(raw_name);
     if had_errors {
-        Err(binread::error::Error::Custom {
-            pos: ro.offset,
-            err: Box::new(format!(
-                "unable to decode String at offset 0x{:08x}",
-                ro.offset
-            )),
-        })
-    } else {
-        Ok(cow.to_string())
+        log::debug!("name at offset 0x{:08x} is not valid UTF-16", ro.offset);
     }
+    Ok(cow.to_string())
+}
+
+/// converts a name, which is stored as UTF-16LE, into UTF-16 code units without any validation
+pub(crate) fn raw_name_to_utf16(raw_name: &[u8]) -> Vec<u16> {
+    raw_name
+        .chunks(2)
+        .map(|c| u16::from_le_bytes([c[0], *c.get(1).unwrap_or(&0)]))
+        .collect()
 }
 
-pub(crate) fn parse_reg_sz(raw_string: &[u8]) -> BinResult<String> {
-    let res = parse_reg_sz_raw(raw_string)?;
+pub(crate) fn parse_reg_sz(raw_string: &[u8], codepage: Codepage) -> BinResult<String> {
+    let res = parse_reg_sz_raw(raw_string, codepage)?;
     Ok(res.trim_end_matches(char::from(0)).to_string())
 }
 
-pub fn parse_reg_sz_raw(raw_string: &[u8]) -> BinResult<String> {
+/// decodes a string, which is expected to be UTF-16LE. If this fails, the string is
+/// decoded using `codepage`.
+pub fn parse_reg_sz_raw(raw_string: &[u8], codepage: Codepage) -> BinResult<String> {
     let (cow, _, had_errors) = UTF_16LE.decode(raw_string);
     if !had_errors {
         Ok(cow.to_string())
     } else {
-        let (cow, _, had_errors) = ISO_8859_15.decode(raw_string);
-        if had_errors {
-            Err(binread::error::Error::Custom {
-                pos: 0,
-                err: Box::new("unable to decode RegSZ string"),
-            })
-        } else {
-            //assert_eq!(raw_string.len(), cow.len());
-            Ok(cow.to_string())
-        }
+        Ok(codepage.decode(raw_string))
     }
 }
 
-pub(crate) fn parse_reg_multi_sz(raw_string: &[u8]) -> BinResult<Vec<String>> {
-    let mut multi_string: Vec<String> = parse_reg_sz_raw(raw_string)?
-        .split('\0')
-        .map(|x| x.to_owned())
-        .collect();
-
-    // due to the way split() works we have an empty string after the last \0 character
-    // and due to the way RegMultiSZ works we have an additional empty string between the
-    // last two \0 characters.
-    // those additional empty strings will be deleted afterwards:
-    assert!(!multi_string.len() >= 2);
-    //assert_eq!(multi_string.last().unwrap().len(), 0);
-    multi_string.pop();
-
-    if multi_string.last().is_some() {
-        // assert_eq!(multi_string.last().unwrap().len(), 0);
-        multi_string.pop();
-    }
+pub(crate) fn parse_reg_multi_sz(raw_string: &[u8], codepage: Codepage) -> BinResult<Vec<String>> {
+    let multi_string = parse_reg_sz_raw(raw_string, codepage)?;
 
-    Ok(multi_string)
+    // a RegMultiSZ is terminated by two \0 characters, but there are values which
+    // are not terminated correctly. So we only strip the terminating \0 characters
+    // (if there are any), but keep empty strings in the middle of the list.
+    let multi_string = multi_string.trim_end_matches(char::from(0));
+    if multi_string.is_empty() {
+        Ok(Vec::new())
+    } else {
+        Ok(multi_string.split('\0').map(|x| x.to_owned()).collect())
+    }
 }
 
 pub(crate) fn parse_timestamp<R: Read + Seek>(
@@ -82,8 +72,25 @@ pub(crate) fn parse_timestamp<R: Read + Seek>(
     _: (),
 ) -> BinResult<DateTime<Utc>> {
     let raw_timestamp: [u8; 8] = reader.read_le()?;
-    let timestamp = WinTimestamp::new(&raw_timestamp).unwrap();
-    Ok(timestamp.to_datetime())
+    Ok(filetime_to_datetime(&raw_timestamp))
+}
+
+/// the number of seconds between 1601-01-01 (the FILETIME epoch) and 1970-01-01
+const FILETIME_UNIX_EPOCH_DIFFERENCE: i64 = 11_644_473_600;
+
+/// converts a FILETIME (100 ns intervals since 1601-01-01) into a timestamp with
+/// microsecond precision. Every 64 bit value represents a valid timestamp.
+pub(crate) fn filetime_to_datetime(raw_timestamp: &[u8; 8]) -> DateTime<Utc> {
+    let micros = u64::from_le_bytes(*raw_timestamp) / 10;
+    let secs = (micros / 1_000_000) as i64 - FILETIME_UNIX_EPOCH_DIFFERENCE;
+    let nanos = (micros % 1_000_000) as u32 * 1000;
+    DateTime::from_timestamp(secs, nanos).unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+/// Windows refuses to convert FILETIME values which have the most significant bit set
+/// (see <https://learn.microsoft.com/en-us/windows/win32/api/timezoneapi/nf-timezoneapi-filetimetosystemtime>)
+pub(crate) fn is_filetime_in_range(raw_timestamp: &[u8; 8]) -> bool {
+    u64::from_le_bytes(*raw_timestamp) <= i64::MAX as u64
 }
 
 pub const U32_FIRST_BIT: u32 = 1 << (u32::BITS - 1);
@@ -106,6 +113,7 @@ pub(crate) fn without_first_bit(val: u32) -> u32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::Datelike;
 
     #[test]
     fn test_has_first_bit_set() {
@@ -127,4 +135,34 @@ mod tests {
         assert_eq!(16, without_first_bit(0x8000_0010));
         assert_eq!(32, without_first_bit(0x8000_0020));
     }
+
+    #[test]
+    fn test_filetime() {
+        let raw_timestamp = [0x53, 0xC7, 0x8B, 0x18, 0xC5, 0xCC, 0xCE, 0x01];
+        assert!(is_filetime_in_range(&raw_timestamp));
+        assert_eq!(
+            filetime_to_datetime(&raw_timestamp).to_string(),
+            "2013-10-19 12:16:53.276040 UTC"
+        );
+
+        assert!(!is_filetime_in_range(&[0xff; 8]));
+
+        assert_eq!(
+            filetime_to_datetime(&[0; 8]).to_string(),
+            "1601-01-01 00:00:00 UTC"
+        );
+        assert_eq!(filetime_to_datetime(&[0xff; 8]).year(), 60056);
+    }
+
+    #[test]
+    fn test_reg_multi_sz() {
+        let utf16 = |s: &str| -> Vec<u8> { s.encode_utf16().flat_map(u16::to_le_bytes).collect() };
+
+        assert_eq!(parse_reg_multi_sz(&utf16("a\0b\0\0"), Codepage::Latin1).unwrap(), vec!["a", "b"]);
+        assert_eq!(parse_reg_multi_sz(&utf16("a\0\0b\0\0"), Codepage::Latin1).unwrap(), vec!["a", "", "b"]);
+        assert_eq!(parse_reg_multi_sz(&utf16("a\0b"), Codepage::Latin1).unwrap(), vec!["a", "b"]);
+        assert_eq!(parse_reg_multi_sz(&utf16("a\0"), Codepage::Latin1).unwrap(), vec!["a"]);
+        assert!(parse_reg_multi_sz(&utf16("\0"), Codepage::Latin1).unwrap().is_empty());
+        assert!(parse_reg_multi_sz(&[], Codepage::Latin1).unwrap().is_empty());
+    }
 }
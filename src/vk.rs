@@ -1,16 +1,24 @@
 
+use crate::data_provenance::{DataCell, DataCellRole, DataProvenance, DataReadError};
 use crate::db::BigData;
+use crate::resource_list::{FullResourceDescriptor, ResourceList, ResourceRequirementsList};
 use crate::cell_with_u8_list::CellWithU8List;
 use crate::util::*;
 use crate::Cell;
+use crate::Codepage;
+use crate::Error;
 use crate::CellHeader;
 use crate::Offset;
+use crate::ParseContext;
+use crate::Result;
 
 use binread::derive_binread;
 use binread::BinResult;
 use binread::ReadOptions;
 use binread::{BinRead, BinReaderExt};
 use bitflags::bitflags;
+use chrono::DateTime;
+use chrono::Utc;
 use std::fmt::Display;
 use std::io::Cursor;
 use std::io::Read;
@@ -53,20 +61,11 @@ pub(crate) enum OffsetOrData {
     /// When the most significant bit is 1, data (4 bytes or less) is stored in
     /// the Data offset field directly (when data contains less than 4 bytes,
     /// it is being stored as is in the beginning of the Data offset field).
-    #[br(pre_assert(u32::has_first_bit_set(&data_size) && ( without_first_bit(data_size) == 3 || without_first_bit(data_size) == 4) ))]
-    U32Data(u32),
-
-    #[br(pre_assert(u32::has_first_bit_set(&data_size) && without_first_bit(data_size) == 2))]
-    #[allow(dead_code)]
-    U16Data(u16, u16),
-
-    #[br(pre_assert(u32::has_first_bit_set(&data_size) && without_first_bit(data_size) == 1))]
-    #[allow(dead_code)]
-    U8Data(u8, u8, u8, u8),
-
-    #[br(pre_assert(u32::has_first_bit_set(&data_size) && without_first_bit(data_size) == 0))]
-    #[allow(dead_code)]
-    None(u32),
+    #[br(pre_assert(u32::has_first_bit_set(&data_size)))]
+    Data(
+        #[br(map = |raw: [u8; 4]| raw[..without_first_bit(data_size) as usize].to_vec())]
+        Vec<u8>,
+    ),
 
     /// When the most significant bit is 0, data is stored in the Cell data
     /// field of another cell (pointed by the Data offset field) or in the Cell
@@ -77,20 +76,24 @@ pub(crate) enum OffsetOrData {
 }
 
 #[derive(BinRead)]
-#[br(magic = b"vk")]
-pub struct KeyValueWithMagic(KeyValue);
+#[br(magic = b"vk", import(ctx: ParseContext))]
+pub struct KeyValueWithMagic(#[br(args(ctx))] KeyValue);
 
 /// Represents a KeyValue as documented in <https://github.com/msuhanov/regf/blob/master/Windows%20registry%20file%20format%20specification.md#key-value>.
 /// 
 #[derive_binread]
 #[allow(dead_code)]
 #[derive(Debug)]
+#[br(import(ctx: ParseContext))]
 pub struct KeyValue {
+    /// offset of the cell which contains this value. This is not part of the
+    /// value record itself and will be set after the value has been read
+    #[br(default)]
+    offset: Offset,
+
     name_length: u16,
 
-    #[br(assert(
-        [U32_FIRST_BIT, U32_FIRST_BIT | 1, U32_FIRST_BIT | 3, U32_FIRST_BIT | 2, U32_FIRST_BIT | 4].contains(&data_size)
-         || ! u32::has_first_bit_set(&data_size), "invalid data size: 0x{:08x}", data_size))]
+    #[br(parse_with=parse_data_size)]
     data_size: u32,
 
     #[br(args(data_size))]
@@ -100,11 +103,10 @@ pub struct KeyValue {
     /// anything in the list above. These are typically seen in the SAM
     /// Registry hives and often correspond to part of a users SID
     /// <https://binaryforay.blogspot.com/2015/01/registry-hive-basics-part-3-vk-records.html>
-    #[br(try)]
-    data_type: Option<KeyValueDataType>,
+    raw_data_type: u32,
 
-    #[br(temp, if(data_type.is_none()))]
-    data_type_spare: u32,
+    #[br(calc(num_traits::FromPrimitive::from_u32(raw_data_type)))]
+    data_type: Option<KeyValueDataType>,
 
     #[br(parse_with=parse_value_flags)]
     flags: KeyValueFlags,
@@ -112,86 +114,199 @@ pub struct KeyValue {
     #[br(temp)]
     spare: u16,
 
+    #[br(count=name_length)]
+    key_name_raw: Vec<u8>,
+
     #[br(   if(name_length>0, "(Default)".to_string()),
-            parse_with=parse_string,
-            count=name_length,
-            args(flags.contains(KeyValueFlags::VALUE_COMP_NAME)))]
+            parse_with=parse_name,
+            args(&key_name_raw, flags.contains(KeyValueFlags::VALUE_COMP_NAME), ctx.codepage()))]
     key_name_string: String,
 
-    #[br(parse_with(parse_registry_value), args(&data_type, &offset_or_data, &data_size))]
+    #[br(parse_with(read_value_data), args(&offset_or_data, &data_size, ctx))]
+    data: ValueData,
+
+    #[br(parse_with(parse_registry_value), args(&raw_data_type, &data_type, &data, ctx.codepage()))]
     value: RegistryValue,
 }
 
-fn parse_registry_value<R: Read + Seek>(
+/// the raw data of a [KeyValue], together with the cells the data has been read from
+#[derive(Debug, Default)]
+struct ValueData {
+    raw: Vec<u8>,
+    provenance: DataProvenance,
+
+    /// error which occurred while reading the data
+    error: Option<String>,
+}
+
+fn read_value_data<R: Read + Seek>(
     reader: &mut R,
     _ro: &ReadOptions,
-    args: (&Option<KeyValueDataType>, &OffsetOrData, &u32),
-) -> BinResult<RegistryValue> {
-    let data_type: &Option<KeyValueDataType> = args.0;
-    let offset_or_data: &OffsetOrData = args.1;
-    let data_size: u32 = args.2 & INV_U32_FIRST_BIT;
+    args: (&OffsetOrData, &u32, ParseContext),
+) -> BinResult<ValueData> {
+    // a value whose data cannot be read is still a value, so we don't fail here
+    Ok(
+        try_read_value_data(reader, args.0, args.1 & INV_U32_FIRST_BIT, args.2).unwrap_or_else(
+            |why| {
+                log::debug!("error while reading value data: {}", why);
+                ValueData {
+                    error: Some(why.to_string()),
+                    ..Default::default()
+                }
+            },
+        ),
+    )
+}
 
+fn try_read_value_data<R: Read + Seek>(
+    reader: &mut R,
+    offset_or_data: &OffsetOrData,
+    data_size: u32,
+    ctx: ParseContext,
+) -> BinResult<ValueData> {
     Ok(match offset_or_data {
-        OffsetOrData::U32Data(val) => RegistryValue::RegDWord(*val),
-        OffsetOrData::U16Data(_, val) => RegistryValue::RegDWord(*val as u32),
-        OffsetOrData::U8Data(_, _, _, val) => RegistryValue::RegDWord(*val as u32),
-        OffsetOrData::None(_) => RegistryValue::RegNone,
+        OffsetOrData::Data(data) => ValueData {
+            raw: data.clone(),
+            ..Default::default()
+        },
+        OffsetOrData::Offset(_) if data_size == 0 => ValueData::default(),
         OffsetOrData::Offset(offset) => {
-            match data_type {
-                None => RegistryValue::RegUnknown,
-                Some(KeyValueDataType::RegNone) => RegistryValue::RegUnknown,
-                Some(dt) => {
-                    let raw_value = if data_size > BIG_DATA_SEGMENT_SIZE {
-                        log::debug!("expecting BIGDATA at 0x{:08x}", offset.0 + 4096);
-
-                        let _offset = reader.seek(SeekFrom::Start(offset.0.into()))?;
-                        let _header: CellHeader = reader.read_le()?;
-                        let bigdata: BigData = reader.read_le()?;
-                        bigdata.bytes
-                    } else {
-                        // don't treat data as Big Data
-                        //eprintln!("reading data of size {} from offset {:08x}", self.data_size(), self.data_offset.val.0 + hive.data_offset());
-                        let _offset = reader.seek(SeekFrom::Start(offset.0.into()))?;
-                        let data: CellWithU8List = reader.read_le_args((Some(data_size as usize),))?;
-                        data.data
-                    };
-
-                    match dt {
-                        KeyValueDataType::RegNone => RegistryValue::RegNone,
-                        KeyValueDataType::RegSZ => {
-                            RegistryValue::RegSZ(parse_reg_sz(&raw_value[..])?)
-                        }
-                        KeyValueDataType::RegExpandSZ => {
-                            RegistryValue::RegExpandSZ(parse_reg_sz(&raw_value[..])?)
-                        }
-                        KeyValueDataType::RegBinary => RegistryValue::RegBinary(raw_value),
-                        KeyValueDataType::RegDWord => {
-                            RegistryValue::RegDWord(Cursor::new(raw_value).read_le()?)
-                        }
-                        KeyValueDataType::RegDWordBigEndian => {
-                            RegistryValue::RegDWordBigEndian(Cursor::new(raw_value).read_be()?)
-                        }
-                        KeyValueDataType::RegLink => RegistryValue::RegNone,
-                        KeyValueDataType::RegMultiSZ => {
-                            RegistryValue::RegMultiSZ(parse_reg_multi_sz(&raw_value[..])?)
-                        }
-                        KeyValueDataType::RegResourceList => RegistryValue::RegNone,
-                        KeyValueDataType::RegFullResourceDescriptor => RegistryValue::RegNone,
-                        KeyValueDataType::RegResourceRequirementsList => RegistryValue::RegNone,
-                        KeyValueDataType::RegQWord => {
-                            RegistryValue::RegQWord(Cursor::new(raw_value).read_le()?)
-                        }
-                        KeyValueDataType::RegFileTime => RegistryValue::RegFileTime,
-                    }
+            // big data records are only used since hive version 1.4
+            if data_size > BIG_DATA_SEGMENT_SIZE && ctx.supports_big_data() {
+                log::debug!("expecting BIGDATA at 0x{:08x}", u64::from(offset.0) + 4096);
+
+                let _offset = reader.seek(SeekFrom::Start(offset.0.into()))?;
+                let header: CellHeader = reader.read_le()?;
+                let bigdata: BigData = reader.read_le()?;
+                let content = bigdata.read_content(reader, data_size as usize)?;
+
+                let mut cells = vec![DataCell::new(
+                    *offset,
+                    header.size(),
+                    header.is_deleted(),
+                    DataCellRole::BigDataHeader,
+                )];
+                cells.extend(content.cells);
+
+                let error = if content.errors.is_empty() {
+                    None
+                } else {
+                    Some(format!(
+                        "big data is incomplete: {}",
+                        content
+                            .errors
+                            .iter()
+                            .map(|e| e.to_string())
+                            .collect::<Vec<_>>()
+                            .join("; ")
+                    ))
+                };
+
+                ValueData {
+                    raw: content.bytes,
+                    provenance: DataProvenance::new(cells, content.errors),
+                    error,
+                }
+            } else {
+                // don't treat data as Big Data
+                let _offset = reader.seek(SeekFrom::Start(offset.0.into()))?;
+                let mut data: CellWithU8List = reader.read_le_args((None,))?;
+                let cell = DataCell::new(
+                    *offset,
+                    data.cell_size(),
+                    data.is_deleted(),
+                    DataCellRole::Data,
+                );
+
+                let mut errors = Vec::new();
+                if data.data.len() < data_size as usize {
+                    errors.push(DataReadError::new(
+                        *offset,
+                        DataCellRole::Data,
+                        format!(
+                            "cell is too small: expected {data_size} bytes, but found only {} bytes",
+                            data.data.len()
+                        ),
+                    ));
+                }
+
+                // cut off the slack space of the cell
+                data.data.truncate(data_size as usize);
+                ValueData {
+                    raw: data.data,
+                    error: errors.first().map(|e| e.to_string()),
+                    provenance: DataProvenance::new(vec![cell], errors),
                 }
             }
         }
     })
 }
 
+fn parse_registry_value<R: Read + Seek>(
+    _reader: &mut R,
+    _ro: &ReadOptions,
+    args: (&u32, &Option<KeyValueDataType>, &ValueData, Codepage),
+) -> BinResult<RegistryValue> {
+    let data = args.2;
+    let result = match (&data.error, args.1) {
+        (Some(error), _) => Err(error.clone()),
+        (None, None) => Ok(RegistryValue::RegUnknown(data.raw.clone())),
+        (None, Some(dt)) => {
+            decode_registry_value(dt, data.raw.clone(), args.3).map_err(|why| why.to_string())
+        }
+    };
+
+    Ok(result.unwrap_or_else(|error| RegistryValue::Invalid {
+        declared_type: Some(*args.0),
+        raw: data.raw.clone(),
+        error,
+    }))
+}
+
+fn decode_registry_value(
+    dt: &KeyValueDataType,
+    raw_value: Vec<u8>,
+    codepage: Codepage,
+) -> Result<RegistryValue> {
+    Ok(match dt {
+        KeyValueDataType::RegNone => RegistryValue::RegNone,
+        KeyValueDataType::RegSZ => RegistryValue::RegSZ(parse_reg_sz(&raw_value[..], codepage)?),
+        KeyValueDataType::RegExpandSZ => RegistryValue::RegExpandSZ(parse_reg_sz(&raw_value[..], codepage)?),
+        KeyValueDataType::RegBinary => RegistryValue::RegBinary(raw_value),
+        KeyValueDataType::RegDWord => RegistryValue::RegDWord(Cursor::new(raw_value).read_le()?),
+        KeyValueDataType::RegDWordBigEndian => {
+            RegistryValue::RegDWordBigEndian(Cursor::new(raw_value).read_be()?)
+        }
+        KeyValueDataType::RegLink => RegistryValue::RegLink(parse_reg_sz(&raw_value[..], codepage)?),
+        KeyValueDataType::RegMultiSZ => {
+            RegistryValue::RegMultiSZ(parse_reg_multi_sz(&raw_value[..], codepage)?)
+        }
+        KeyValueDataType::RegResourceList => {
+            RegistryValue::RegResourceList(ResourceList::parse(&raw_value[..])?)
+        }
+        KeyValueDataType::RegFullResourceDescriptor => RegistryValue::RegFullResourceDescriptor(
+            FullResourceDescriptor::parse(&raw_value[..])?,
+        ),
+        KeyValueDataType::RegResourceRequirementsList => {
+            RegistryValue::RegResourceRequirementsList(ResourceRequirementsList::parse(
+                &raw_value[..],
+            )?)
+        }
+        KeyValueDataType::RegQWord => RegistryValue::RegQWord(Cursor::new(raw_value).read_le()?),
+        KeyValueDataType::RegFileTime => {
+            let raw_timestamp: [u8; 8] = Cursor::new(raw_value).read_le()?;
+            if is_filetime_in_range(&raw_timestamp) {
+                RegistryValue::RegFileTime(filetime_to_datetime(&raw_timestamp))
+            } else {
+                RegistryValue::RegFileTimeOutOfRange(raw_timestamp)
+            }
+        }
+    })
+}
+
 /// Possible data types of the data belonging to a [`KeyValue`].
-/// https://docs.microsoft.com/en-us/windows/win32/sysinfo/registry-value-types
-#[derive(BinRead, Debug)]
+/// <https://docs.microsoft.com/en-us/windows/win32/sysinfo/registry-value-types>
+#[derive(BinRead, Debug, Clone, Copy, Eq, PartialEq, num_derive::FromPrimitive)]
 #[br(repr=u32)]
 pub enum KeyValueDataType {
     /// Data with no particular type
@@ -258,7 +373,11 @@ impl Display for KeyValueDataType {
 #[derive(Debug)]
 pub enum RegistryValue {
     RegNone,
-    RegUnknown,
+
+    /// a value of a data type which is not listed in [`KeyValueDataType`]. Such data types are
+    /// used by SAM hives (where the type field contains a RID) or by application hives. Use
+    /// [`KeyValue::raw_data_type`] to obtain the data type. This contains the raw bytes of the value.
+    RegUnknown(Vec<u8>),
     RegSZ(String),
     RegExpandSZ(String),
     RegBinary(Vec<u8>),
@@ -266,17 +385,35 @@ pub enum RegistryValue {
     RegDWordBigEndian(u32),
     RegLink(String),
     RegMultiSZ(Vec<String>),
-    RegResourceList(String),
-    RegFullResourceDescriptor(String),
-    RegResourceRequirementsList(String),
+    RegResourceList(ResourceList),
+    RegFullResourceDescriptor(FullResourceDescriptor),
+    RegResourceRequirementsList(ResourceRequirementsList),
     RegQWord(u64),
-    RegFileTime,
+    RegFileTime(DateTime<Utc>),
+
+    /// a `REG_FILETIME` value which cannot be converted into a timestamp;
+    /// this contains the raw bytes of the value
+    RegFileTimeOutOfRange([u8; 8]),
+
+    /// a value which could not be decoded, either because its data could not
+    /// be read or because the data does not match the declared data type
+    Invalid {
+        /// the data type as declared in the value record, or [`None`] if
+        /// the value record itself could not be read
+        declared_type: Option<u32>,
+
+        /// the raw data, as far as it could be read
+        raw: Vec<u8>,
+
+        /// description of what went wrong
+        error: String,
+    },
 }
 
 impl Display for RegistryValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            RegistryValue::RegUnknown => write!(f, "Unknown"),
+            RegistryValue::RegUnknown(val) => write!(f, "Unknown {val:?}"),
             RegistryValue::RegNone => write!(f, "None"),
             RegistryValue::RegSZ(val) => write!(f, "{val:?}"),
             RegistryValue::RegExpandSZ(val) => write!(f, "{val:?}"),
@@ -291,21 +428,125 @@ impl Display for RegistryValue {
             RegistryValue::RegDWordBigEndian(val) => write!(f, "0x{:08x}", val),
             RegistryValue::RegLink(val) => write!(f, "{val:?}"),
             RegistryValue::RegMultiSZ(val) => write!(f, "{:?}", val),
-            RegistryValue::RegResourceList(val) => write!(f, "{val:?}"),
-            RegistryValue::RegFullResourceDescriptor(val) => write!(f, "{val:?}"),
-            RegistryValue::RegResourceRequirementsList(val) => write!(f, "{val:?}"),
+            RegistryValue::RegResourceList(val) => write!(f, "{val}"),
+            RegistryValue::RegFullResourceDescriptor(val) => write!(f, "{val}"),
+            RegistryValue::RegResourceRequirementsList(val) => write!(f, "{val}"),
             RegistryValue::RegQWord(val) => write!(f, "0x{:016x}", val),
-            RegistryValue::RegFileTime => todo!(),
+            RegistryValue::RegFileTime(val) => write!(f, "{val}"),
+            RegistryValue::RegFileTimeOutOfRange(val) => {
+                write!(f, "invalid FILETIME 0x{:016x}", u64::from_le_bytes(*val))
+            }
+            RegistryValue::Invalid { raw, error, .. } => {
+                write!(f, "invalid value ({error}): {raw:?}")
+            }
         }
     }
 }
 
 impl KeyValue {
+    /// creates a placeholder for a value record which could not be read
+    pub(crate) fn invalid(offset: Offset, error: String) -> Self {
+        Self {
+            offset,
+            name_length: 0,
+            data_size: 0,
+            offset_or_data: OffsetOrData::Offset(Offset(u32::MAX)),
+            raw_data_type: 0,
+            data_type: None,
+            flags: KeyValueFlags::empty(),
+            key_name_raw: Vec::new(),
+            key_name_string: String::new(),
+            data: ValueData {
+                error: Some(error.clone()),
+                ..Default::default()
+            },
+            value: RegistryValue::Invalid {
+                declared_type: None,
+                raw: Vec::new(),
+                error,
+            },
+        }
+    }
+
+    pub(crate) fn with_offset(mut self, offset: Offset) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Returns the offset of the cell which contains this value. This offset
+    /// can be used to read this value again, using [`Hive::value_at`](crate::Hive::value_at).
+    pub fn offset(&self) -> Offset {
+        self.offset
+    }
+
+    /// Returns [true] if the value could not be decoded. In this case, [`KeyValue::value`]
+    /// returns a [`RegistryValue::Invalid`], which contains further information.
+    pub fn is_invalid(&self) -> bool {
+        matches!(self.value, RegistryValue::Invalid { .. })
+    }
+
+    /// Returns [true] if this value is a tombstone value, which hides the value with
+    /// the same name in lower layers of a layered hive
+    pub fn is_tombstone(&self) -> bool {
+        self.flags.contains(KeyValueFlags::IS_TOMBSTONE)
+    }
+
     /// Returns the name of this value
     pub fn name(&self) -> &str {
         &self.key_name_string
     }
 
+    /// Returns the name of this value as it is stored in the hive. Depending on
+    /// [`is_name_compressed`](Self::is_name_compressed), this is either (extended) ASCII
+    /// or UTF-16LE. The default value has an empty name.
+    pub fn raw_name(&self) -> &[u8] {
+        &self.key_name_raw
+    }
+
+    /// Returns [true] if the name is stored as (extended) ASCII instead of UTF-16LE.
+    /// Such names are decoded using the [`Codepage`](crate::Codepage) of the hive.
+    pub fn is_name_compressed(&self) -> bool {
+        self.flags.contains(KeyValueFlags::VALUE_COMP_NAME)
+    }
+
+    /// Returns the name of this value as UTF-16 code units. In contrast to
+    /// [`name`](Self::name), this keeps names which are not valid UTF-16 (e.g. which
+    /// contain unpaired surrogates) unchanged. Every byte of a compressed name is
+    /// returned as one code unit, regardless of the [`Codepage`](crate::Codepage) of the hive.
+    pub fn name_utf16(&self) -> Vec<u16> {
+        if self.is_name_compressed() {
+            self.key_name_raw.iter().map(|b| u16::from(*b)).collect()
+        } else {
+            raw_name_to_utf16(&self.key_name_raw)
+        }
+    }
+
+    /// Returns the name which is used to identify this value: compressed names are used
+    /// after they have been decoded with the [`Codepage`](crate::Codepage) of the hive,
+    /// all other names are used as they are stored.
+    pub(crate) fn decoded_name_utf16(&self) -> Vec<u16> {
+        if self.is_name_compressed() {
+            self.key_name_string.encode_utf16().collect()
+        } else {
+            raw_name_to_utf16(&self.key_name_raw)
+        }
+    }
+
+    /// Returns [true] if the name could not be decoded without loss, which is the case for
+    /// names which are not valid UTF-16, and for compressed names which contain bytes that are
+    /// not defined in the [`Codepage`](crate::Codepage) of the hive. In this case,
+    /// [`name`](Self::name) contains replacement characters, and [`name_utf16`](Self::name_utf16) or
+    /// [`raw_name`](Self::raw_name) should be used to identify the value.
+    pub fn is_name_lossy(&self) -> bool {
+        if self.is_name_compressed() {
+            // no codepage maps a defined byte to the replacement character
+            self.key_name_string.contains(char::REPLACEMENT_CHARACTER)
+        } else {
+            !self.key_name_raw.len().is_multiple_of(2)
+                || String::from_utf16(&self.name_utf16()).is_err()
+        }
+    }
+
     /// Returns [true] if this value is resident, which means that it is stored directly in the offset field.
     pub fn is_resident(&self) -> bool {
         u32::has_first_bit_set(&self.data_size)
@@ -322,11 +563,49 @@ impl KeyValue {
         &self.value
     }
 
-    /// Returns the datatype
+    /// Returns the datatype, or [`None`] if the datatype is not listed in [`KeyValueDataType`]
     pub fn data_type(&self) -> Option<&KeyValueDataType> {
         self.data_type.as_ref()
     }
 
+    /// Returns the datatype as it is stored in the hive
+    pub fn raw_data_type(&self) -> u32 {
+        self.raw_data_type
+    }
+
+    /// Returns the raw bytes of the data, exactly as they are stored in the hive
+    /// (but without any slack space of the cells which contain the data)
+    pub fn raw_data(&self) -> &[u8] {
+        &self.data.raw[..]
+    }
+
+    /// Returns the offset of the cell which contains the data, or [`None`] if the data is resident
+    /// or if there is no data at all. For big data, this is the offset of the big data record.
+    pub fn data_offset(&self) -> Option<Offset> {
+        match self.offset_or_data {
+            OffsetOrData::Offset(offset) if self.data_size() > 0 => Some(offset),
+            _ => None,
+        }
+    }
+
+    /// Returns the list of cells which have been read to obtain the data of this value
+    pub fn data_provenance(&self) -> &DataProvenance {
+        &self.data.provenance
+    }
+}
+
+/// data which is stored in the data offset field has a size of at most 4 bytes
+fn parse_data_size<R: Read + Seek>(
+    reader: &mut R,
+    _ro: &ReadOptions,
+    _: (),
+) -> BinResult<u32> {
+    let pos = reader.stream_position()?;
+    let data_size: u32 = reader.read_le()?;
+    if u32::has_first_bit_set(&data_size) && without_first_bit(data_size) > 4 {
+        return Err(Error::InvalidDataSize { offset: pos, data_size }.into_binread_error(pos));
+    }
+    Ok(data_size)
 }
 
 fn parse_value_flags<R: Read + Seek>(
@@ -338,8 +617,86 @@ fn parse_value_flags<R: Read + Seek>(
     Ok(KeyValueFlags::from_bits_truncate(raw_value))
 }
 
-impl From<Cell<KeyValueWithMagic, ()>> for KeyValue {
-    fn from(cell: Cell<KeyValueWithMagic, ()>) -> Self {
+impl From<Cell<KeyValueWithMagic, (ParseContext,)>> for KeyValue {
+    fn from(cell: Cell<KeyValueWithMagic, (ParseContext,)>) -> Self {
         cell.into_data().0
     }
 }
+
+impl From<Cell<KeyValueWithMagic, (ParseContext,)>> for KeyValueWithMagic {
+    fn from(cell: Cell<KeyValueWithMagic, (ParseContext,)>) -> Self {
+        cell.into_data()
+    }
+}
+
+impl From<KeyValueWithMagic> for KeyValue {
+    fn from(kvm: KeyValueWithMagic) -> Self {
+        kvm.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resident_value(data_type: u32, data: &[u8]) -> KeyValue {
+        let mut raw = Vec::new();
+        raw.extend(b"vk");
+        raw.extend(1u16.to_le_bytes());
+        raw.extend((U32_FIRST_BIT | data.len() as u32).to_le_bytes());
+        raw.extend(data);
+        raw.extend(vec![0; 4 - data.len()]);
+        raw.extend(data_type.to_le_bytes());
+        raw.extend(KeyValueFlags::VALUE_COMP_NAME.bits().to_le_bytes());
+        raw.extend(0u16.to_le_bytes());
+        raw.extend(b"x");
+        let kv: KeyValueWithMagic = Cursor::new(raw)
+            .read_le_args((ParseContext::default(),))
+            .unwrap();
+        kv.0
+    }
+
+    #[test]
+    fn test_resident_data() {
+        let kv = resident_value(3, &[0xab, 0xcd]);
+        assert!(kv.is_resident());
+        assert!(matches!(kv.value(), RegistryValue::RegBinary(v) if v == &[0xab, 0xcd]));
+
+        let kv = resident_value(1, &[b'a', 0, 0, 0]);
+        assert!(matches!(kv.value(), RegistryValue::RegSZ(v) if v == "a"));
+
+        let kv = resident_value(4, &[0x2a, 0, 0, 0]);
+        assert!(matches!(kv.value(), RegistryValue::RegDWord(0x2a)));
+
+        let kv = resident_value(0, &[]);
+        assert!(matches!(kv.value(), RegistryValue::RegNone));
+
+        let kv = resident_value(7, &[b'a', 0]);
+        assert!(matches!(kv.value(), RegistryValue::RegMultiSZ(v) if v == &["a"]));
+    }
+
+    #[test]
+    fn test_unknown_data_type() {
+        let kv = resident_value(0x3e9, &[1, 2, 3]);
+        assert_eq!(kv.raw_data_type(), 0x3e9);
+        assert!(kv.data_type().is_none());
+        assert!(matches!(kv.value(), RegistryValue::RegUnknown(v) if v == &[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let kv = resident_value(4, &[0x2a, 0]);
+        assert!(kv.is_invalid());
+        match kv.value() {
+            RegistryValue::Invalid {
+                declared_type,
+                raw,
+                error: _,
+            } => {
+                assert_eq!(declared_type, &Some(4));
+                assert_eq!(raw, &[0x2a, 0]);
+            }
+            _ => panic!("expected an invalid value"),
+        }
+    }
+}
@@ -4,7 +4,10 @@ use std::{
     path::PathBuf,
 };
 
-use nt_hive2::{transactionlog::TransactionLog, ContainsHive, Hive, BASEBLOCK_SIZE};
+use nt_hive2::{
+    transactionlog::{ApplicationResult, TransactionLog, TransactionLogsEntry},
+    BaseBlock, ContainsHive, Hive, ReorganizationType, BASEBLOCK_SIZE,
+};
 
 #[test]
 fn test_cleanhive_plain() {
@@ -53,7 +56,8 @@ fn test_cleanhive_with_log1() {
     .unwrap()
     .with_transaction_log(TransactionLog::try_from(File::open(&log1_path).unwrap()).unwrap())
     .unwrap()
-    .apply_logs();
+    .apply_logs()
+    .unwrap();
 
     assert!(hive.is_checksum_valid().unwrap());
 }
@@ -82,7 +86,8 @@ fn test_cleanhive_with_log1_and_log2() {
     .unwrap()
     .with_transaction_log(TransactionLog::try_from(File::open(&log2_path).unwrap()).unwrap())
     .unwrap()
-    .apply_logs();
+    .apply_logs()
+    .unwrap();
 
     assert!(hive.is_checksum_valid().unwrap());
 
@@ -95,3 +100,98 @@ fn test_cleanhive_with_log1_and_log2() {
         .treat_hive_as_clean();
     assert!(new_hive.is_checksum_valid().unwrap());
 }
+
+#[test]
+fn test_base_block_fields() {
+    let mut hive_path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    hive_path.push("tests");
+    hive_path.push("data");
+    hive_path.push("NewDirtyHive1");
+    hive_path.push("NewDirtyHive");
+
+    let hive = Hive::new(
+        File::open(&hive_path).unwrap(),
+        nt_hive2::HiveParseMode::NormalWithBaseBlock,
+    )
+    .unwrap()
+    .treat_hive_as_clean();
+    let base_block = hive.base_block().unwrap();
+
+    assert_eq!(
+        base_block.file_name(),
+        "ers\\user\\Desktop\\1\\NewDirtyHive"
+    );
+    assert!(base_block.has_valid_guids());
+    assert_eq!(
+        base_block.rm_id().to_string(),
+        "68BEF321-011A-11E7-B258-E6983F8AB8A5"
+    );
+    assert_eq!(
+        base_block.tm_id().to_string(),
+        "68BEF322-011A-11E7-B258-E6983F8AB8A5"
+    );
+    assert!(base_block.flags().is_empty());
+    assert_eq!(
+        base_block
+            .last_reorganized_timestamp()
+            .unwrap()
+            .to_rfc3339(),
+        "2017-03-04T20:51:40.034310+00:00"
+    );
+    assert_eq!(
+        base_block.last_reorganization_type(),
+        Some(ReorganizationType::AccessBitsCleared)
+    );
+    assert!(!base_block.is_offline_registry());
+    assert!(base_block.serialization_timestamp().is_none());
+}
+
+#[test]
+fn test_outdated_log_entries() {
+    let mut data_path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    data_path.push("tests");
+    data_path.push("data");
+    data_path.push("NewDirtyHive1");
+
+    let mut hive_path = data_path.clone();
+    let mut log2_path = data_path.clone();
+    hive_path.push("NewDirtyHive");
+    log2_path.push("NewDirtyHive.LOG2");
+
+    // the entries of LOG2 have the sequence numbers 3, 4 and 5. We pretend that the
+    // hive has already been written with sequence number 4, but has no secondary sequence number
+    let mut data = std::fs::read(&hive_path).unwrap();
+    data[0x04..0x08].copy_from_slice(&4u32.to_le_bytes());
+    data[0x08..0x0c].copy_from_slice(&0u32.to_le_bytes());
+    let checksum = data[..0x1fc]
+        .chunks(4)
+        .fold(0, |acc, c| acc ^ u32::from_le_bytes(c.try_into().unwrap()));
+    data[0x1fc..0x200].copy_from_slice(&checksum.to_le_bytes());
+
+    let mut hive = Hive::new(
+        Cursor::new(data),
+        nt_hive2::HiveParseMode::NormalWithBaseBlock,
+    )
+    .unwrap();
+    let entries: Vec<TransactionLogsEntry> =
+        TransactionLog::try_from(File::open(&log2_path).unwrap())
+            .unwrap()
+            .into();
+    assert_eq!(*entries[0].sequence_number(), 3);
+    assert_eq!(*entries[1].sequence_number(), 4);
+
+    // the first entry is older than the hive, so it must not be applied
+    assert!(matches!(
+        hive.apply_transaction_log(entries[0].clone()),
+        Ok(ApplicationResult::SequenceNumberDoesNotMatch)
+    ));
+    assert_eq!(*hive.base_block().unwrap().primary_sequence_number(), 4);
+
+    assert!(matches!(
+        hive.apply_transaction_log(entries[1].clone()),
+        Ok(ApplicationResult::Applied)
+    ));
+    let base_block = hive.base_block().unwrap();
+    assert_eq!(*base_block.primary_sequence_number(), 4);
+    assert_eq!(*base_block.secondary_sequence_number(), 4);
+}
@@ -0,0 +1,1572 @@
+use std::io::Cursor;
+use std::{fs::File, path::PathBuf};
+
+use nt_hive2::{
+    BaseBlock, CleanHive, Codepage, DataCellRole, Error, Hive, HiveParseMode, KeyNode,
+    KeyNodeFlags, LayeredHive, NameComparison, Offset, PathTermination, RegistryValue, SubPath,
+    SubkeysListError, SubkeysListIssue, SymlinkMode, UnsupportedVersion,
+};
+
+fn testhive() -> Hive<File, CleanHive> {
+    let mut hive_path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    hive_path.push("tests");
+    hive_path.push("data");
+    hive_path.push("testhive");
+
+    Hive::new(
+        File::open(&hive_path).unwrap(),
+        HiveParseMode::NormalWithBaseBlock,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_security_descriptor() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let key_security = root_key.security_descriptor(&mut hive).unwrap();
+    let descriptor = key_security.security_descriptor();
+
+    assert!(key_security.reference_count() > 0);
+    assert_eq!(descriptor.owner().unwrap().to_string(), "S-1-5-32-544");
+
+    let trustees: Vec<_> = descriptor
+        .dacl()
+        .unwrap()
+        .aces()
+        .iter()
+        .map(|ace| ace.sid().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        trustees,
+        vec!["S-1-5-18", "S-1-5-32-544", "S-1-1-0", "S-1-5-12"]
+    );
+}
+
+#[test]
+fn test_security_descriptor_with_oversized_size() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let security_offset = root_key.security_offset();
+
+    // declare a size of nearly 4 GiB, which must not be allocated
+    let mut data = testhive_with_minor_version(5).into_inner();
+    let size = 0x1000 + security_offset.0 as usize + 4 + 0x10;
+    data[size..size + 4].copy_from_slice(&0xffff_fff0u32.to_le_bytes());
+
+    let mut hive = Hive::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap();
+    let root_key = hive.root_key_node().unwrap();
+    assert!(matches!(
+        root_key.security_descriptor(&mut hive),
+        Err(Error::CellTooSmall { offset, .. }) if offset == security_offset
+    ));
+}
+
+#[test]
+fn test_class_name() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    assert!(root_key.class_name(&mut hive).unwrap().is_none());
+}
+
+/// returns a copy of testhive, where the class name of `data-test` refers to the
+/// data cell of its value `reg-sz`, which contains "sz-test"
+fn testhive_with_class_name(
+    class_name_length: u16,
+    delete_cell: bool,
+) -> Hive<Cursor<Vec<u8>>, CleanHive> {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key.subkey("data-test", &mut hive).unwrap().unwrap();
+    let key_offset = key.borrow().offset();
+    let data_offset = key
+        .borrow()
+        .values()
+        .iter()
+        .find(|v| v.name() == "reg-sz")
+        .unwrap()
+        .data_offset()
+        .unwrap();
+
+    let mut data = testhive_with_minor_version(5).into_inner();
+    let nk = 0x1000 + key_offset.0 as usize + 4;
+    data[nk + 0x30..nk + 0x34].copy_from_slice(&data_offset.0.to_le_bytes());
+    data[nk + 0x4a..nk + 0x4c].copy_from_slice(&class_name_length.to_le_bytes());
+
+    if delete_cell {
+        let cell = 0x1000 + data_offset.0 as usize;
+        let size = i32::from_le_bytes(data[cell..cell + 4].try_into().unwrap());
+        data[cell..cell + 4].copy_from_slice(&size.abs().to_le_bytes());
+    }
+
+    Hive::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap()
+}
+
+#[test]
+fn test_class_name_cells() {
+    let read_class_name = |mut hive: Hive<Cursor<Vec<u8>>, CleanHive>| {
+        let root_key = hive.root_key_node().unwrap();
+        let key = root_key.subkey("data-test", &mut hive).unwrap().unwrap();
+        let class_name = key.borrow().class_name(&mut hive);
+        class_name
+    };
+
+    let class_name = read_class_name(testhive_with_class_name(14, false));
+    assert_eq!(class_name.unwrap().as_deref(), Some("sz-test"));
+
+    // only the first bytes of the cell are used
+    let class_name = read_class_name(testhive_with_class_name(4, false));
+    assert_eq!(class_name.unwrap().as_deref(), Some("sz"));
+
+    // class names in deleted cells are still available
+    let class_name = read_class_name(testhive_with_class_name(14, true));
+    assert_eq!(class_name.unwrap().as_deref(), Some("sz-test"));
+
+    let class_name = read_class_name(testhive_with_class_name(0x1000, false));
+    assert!(matches!(
+        class_name,
+        Err(Error::CellTooSmall {
+            expected: 0x1000,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_subpath_following_links() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key
+        .subpath_with_mode(
+            "subpath-test\\with-two-levels-of-subkeys\\subkey1",
+            &mut hive,
+            SymlinkMode::Follow,
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(key.borrow().name(), "subkey1");
+    assert!(!key.borrow().is_symbolic_link());
+    assert!(key.borrow().symbolic_link_target().is_none());
+}
+
+/// turns the key `subkey-test\\<name>` into a symbolic link to `target`
+fn make_symbolic_link(data: &mut Vec<u8>, name: &str, target: &str) {
+    let mut hive = testhive();
+    let key_offset = hive
+        .root_key_node()
+        .unwrap()
+        .subpath(format!("subkey-test\\{name}").as_str(), &mut hive)
+        .unwrap()
+        .unwrap()
+        .borrow()
+        .offset();
+
+    let target: Vec<u8> = target.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let target_size = target.len() as u32;
+    let data_offset = append_hbin(data, &[target])[0];
+    let vk = vk_cell("SymbolicLinkValue", 6, target_size, data_offset.0);
+    let vk_offset = append_hbin(data, &[vk])[0];
+    let values_list_offset = append_hbin(data, &[vk_offset.0.to_le_bytes().to_vec()])[0];
+
+    let nk = 0x1000 + key_offset.0 as usize + 4;
+    data[nk + 2] |= 0x10; // KEY_SYM_LINK
+    data[nk + 0x24..nk + 0x28].copy_from_slice(&1u32.to_le_bytes());
+    data[nk + 0x28..nk + 0x2c].copy_from_slice(&values_list_offset.0.to_le_bytes());
+}
+
+fn testhive_with_symbolic_links() -> Hive<Cursor<Vec<u8>>, CleanHive> {
+    let mut data = testhive_with_minor_version(5).into_inner();
+    make_symbolic_link(
+        &mut data,
+        "Key0",
+        "\\REGISTRY\\MACHINE\\TESTHIVE\\subpath-test",
+    );
+
+    // a chain of links
+    make_symbolic_link(
+        &mut data,
+        "key1",
+        "\\REGISTRY\\MACHINE\\TESTHIVE\\subkey-test\\Key0",
+    );
+
+    // a link which can be followed several times
+    make_symbolic_link(
+        &mut data,
+        "Key10",
+        "\\REGISTRY\\MACHINE\\TESTHIVE\\subkey-test",
+    );
+
+    // a loop
+    make_symbolic_link(
+        &mut data,
+        "Key100",
+        "\\REGISTRY\\MACHINE\\TESTHIVE\\subkey-test\\key101",
+    );
+    make_symbolic_link(
+        &mut data,
+        "key101",
+        "\\REGISTRY\\MACHINE\\TESTHIVE\\subkey-test\\Key100",
+    );
+
+    // a link into another hive, whose path also exists in this hive
+    make_symbolic_link(
+        &mut data,
+        "Key11",
+        "\\REGISTRY\\MACHINE\\OTHERHIVE\\subpath-test",
+    );
+
+    let mut hive = Hive::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap();
+    hive.set_mount_point("\\REGISTRY\\MACHINE\\TESTHIVE");
+    hive
+}
+
+#[test]
+fn test_symbolic_links() {
+    let mut hive = testhive_with_symbolic_links();
+    let root_key = hive.root_key_node().unwrap();
+
+    let link = root_key
+        .subpath("subkey-test\\Key0", &mut hive)
+        .unwrap()
+        .unwrap();
+    assert!(link.borrow().is_symbolic_link());
+    assert_eq!(
+        link.borrow().symbolic_link_target(),
+        Some("\\REGISTRY\\MACHINE\\TESTHIVE\\subpath-test")
+    );
+    assert!(matches!(
+        link.borrow().values()[0].value(),
+        RegistryValue::RegLink(target) if target == "\\REGISTRY\\MACHINE\\TESTHIVE\\subpath-test"
+    ));
+    assert!(root_key
+        .subpath("subkey-test\\Key0\\with-two-levels-of-subkeys", &mut hive)
+        .unwrap()
+        .is_none());
+
+    let key = root_key
+        .subpath_with_mode(
+            "subkey-test\\Key0\\with-two-levels-of-subkeys\\subkey1",
+            &mut hive,
+            SymlinkMode::Follow,
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(key.borrow().name(), "subkey1");
+
+    let key = root_key
+        .subpath_with_mode("subkey-test\\key1", &mut hive, SymlinkMode::Follow)
+        .unwrap()
+        .unwrap();
+    assert_eq!(key.borrow().name(), "subpath-test");
+
+    // the same link is followed twice, which is not a loop
+    let key = root_key
+        .subpath_with_mode(
+            "subkey-test\\Key10\\Key10\\Key0\\with-two-levels-of-subkeys",
+            &mut hive,
+            SymlinkMode::Follow,
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(key.borrow().name(), "with-two-levels-of-subkeys");
+
+    let result = root_key.subpath_with_mode("subkey-test\\Key100", &mut hive, SymlinkMode::Follow);
+    match result {
+        Err(Error::SymbolicLinkLoop { target, .. }) => {
+            assert!(target.ends_with("key101") || target.ends_with("Key100"))
+        }
+        _ => panic!("expected a symbolic link loop"),
+    }
+
+    let result = root_key.subpath_with_mode("subkey-test\\Key11", &mut hive, SymlinkMode::Follow);
+    assert!(matches!(
+        result,
+        Err(Error::SymbolicLinkIntoOtherHive { target, .. }) if target.contains("OTHERHIVE")
+    ));
+
+    // without a mount point, no link target can be found in the hive
+    let mut hive = Hive::new(hive.data, HiveParseMode::NormalWithBaseBlock).unwrap();
+    let result = root_key.subpath_with_mode("subkey-test\\key1", &mut hive, SymlinkMode::Follow);
+    assert!(matches!(
+        result,
+        Err(Error::SymbolicLinkIntoOtherHive { .. })
+    ));
+}
+
+#[test]
+fn test_data_provenance() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+
+    let data_test = root_key.subpath("data-test", &mut hive).unwrap().unwrap();
+    let data_test = data_test.borrow();
+    let binary = data_test
+        .values()
+        .iter()
+        .find(|v| v.name() == "binary")
+        .unwrap();
+    assert_eq!(binary.raw_data(), &[1, 2, 3, 4, 5]);
+    assert!(binary.data_offset().is_some());
+    assert_eq!(binary.data_provenance().cells().len(), 1);
+    assert_eq!(
+        binary.data_provenance().cells()[0].role(),
+        DataCellRole::Data
+    );
+
+    let big_data_test = root_key
+        .subpath("big-data-test", &mut hive)
+        .unwrap()
+        .unwrap();
+    let big_data_test = big_data_test.borrow();
+    let value = big_data_test
+        .values()
+        .iter()
+        .find(|v| v.name() == "C")
+        .unwrap();
+    let provenance = value.data_provenance();
+    assert_eq!(provenance.cells()[0].role(), DataCellRole::BigDataHeader);
+    assert_eq!(provenance.cells()[1].role(), DataCellRole::SegmentList);
+    assert!(provenance.data_cells().count() > 1);
+}
+
+#[test]
+fn test_all_values() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let data_test = root_key.subpath("data-test", &mut hive).unwrap().unwrap();
+    let data_test = data_test.borrow();
+
+    assert_eq!(data_test.values().len(), 8);
+    assert!(data_test.values().iter().all(|v| !v.is_invalid()));
+
+    let mut offsets: Vec<_> = data_test.values().iter().map(|v| v.offset()).collect();
+    offsets.sort();
+    offsets.dedup();
+    assert_eq!(offsets.len(), 8);
+}
+
+#[test]
+fn test_big_data() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let big_data_test = root_key
+        .subpath("big-data-test", &mut hive)
+        .unwrap()
+        .unwrap();
+
+    for (name, byte) in [("A", b'A'), ("B", b'B'), ("C", b'C')] {
+        let big_data_test = big_data_test.borrow();
+        let value = big_data_test
+            .values()
+            .iter()
+            .find(|v| v.name() == name)
+            .unwrap();
+        assert!(value.data_provenance().errors().is_empty());
+        assert_eq!(value.raw_data().len(), value.data_size() as usize);
+        assert!(value.raw_data().iter().all(|b| *b == byte));
+        assert!(
+            matches!(value.value(), RegistryValue::RegBinary(v) if v.len() == value.data_size() as usize)
+        );
+    }
+}
+
+#[test]
+fn test_big_data_with_oversized_data_size() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let big_data_test = root_key
+        .subpath("big-data-test", &mut hive)
+        .unwrap()
+        .unwrap();
+    let (value_offset, data_size) = big_data_test
+        .borrow()
+        .values()
+        .iter()
+        .find(|v| v.name() == "C")
+        .map(|v| (v.offset(), v.data_size()))
+        .unwrap();
+
+    // declare a size of nearly 2 GiB, which must not be allocated
+    let mut data = testhive_with_minor_version(5).into_inner();
+    let size = 0x1000 + value_offset.0 as usize + 4 + 4;
+    assert_eq!(&data[size..size + 4], &data_size.to_le_bytes());
+    data[size..size + 4].copy_from_slice(&0x7fff_fff0u32.to_le_bytes());
+
+    let mut hive = Hive::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap();
+    let value = hive.value_at(value_offset).unwrap();
+    assert!(value.is_invalid());
+    assert!(!value.data_provenance().errors().is_empty());
+    assert!(value.raw_data().len() >= data_size as usize);
+    assert!(value.raw_data().len() < 0x10_0000);
+}
+
+#[test]
+fn test_big_data_with_missing_segment() {
+    const SEGMENT_SIZE: usize = 16344;
+    let data_size = 3 * SEGMENT_SIZE - 100;
+
+    // create a big data record with three segments, the second of which is missing
+    let mut data = testhive_with_minor_version(5).into_inner();
+    let segments = append_hbin(
+        &mut data,
+        &[
+            vec![1; SEGMENT_SIZE],
+            vec![2; SEGMENT_SIZE],
+            vec![3; SEGMENT_SIZE],
+        ],
+    );
+    let segment_list = [segments[0], Offset(0x7fff_fff0), segments[2]]
+        .iter()
+        .flat_map(|o| o.0.to_le_bytes())
+        .collect();
+    let segment_list_offset = append_hbin(&mut data, &[segment_list])[0];
+    let mut db = b"db".to_vec();
+    db.extend(3u16.to_le_bytes());
+    db.extend(segment_list_offset.0.to_le_bytes());
+    let db_offset = append_hbin(&mut data, &[db])[0];
+    let vk = vk_cell("D", 3, data_size as u32, db_offset.0);
+    let vk_offset = append_hbin(&mut data, &[vk])[0];
+
+    let mut hive = Hive::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap();
+    let value = hive.value_at(vk_offset).unwrap();
+    assert!(value.is_invalid());
+    let errors = value.data_provenance().errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].offset(), Offset(0x7fff_fff0));
+
+    // the bytes of the third segment must stay at their offset
+    let raw_data = value.raw_data();
+    assert_eq!(raw_data.len(), data_size);
+    assert!(raw_data[..SEGMENT_SIZE].iter().all(|b| *b == 1));
+    assert!(raw_data[SEGMENT_SIZE..2 * SEGMENT_SIZE]
+        .iter()
+        .all(|b| *b == 0));
+    assert!(raw_data[2 * SEGMENT_SIZE..].iter().all(|b| *b == 3));
+}
+
+/// returns the testhive, but with a modified minor version in its base block
+fn testhive_with_minor_version(minor_version: u32) -> Cursor<Vec<u8>> {
+    let mut hive_path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    hive_path.push("tests");
+    hive_path.push("data");
+    hive_path.push("testhive");
+    let mut data = std::fs::read(hive_path).unwrap();
+
+    data[0x18..0x1c].copy_from_slice(&minor_version.to_le_bytes());
+    update_checksum(&mut data);
+    Cursor::new(data)
+}
+
+fn update_checksum(data: &mut [u8]) {
+    let checksum = data[..0x1fc]
+        .chunks(4)
+        .fold(0, |acc, c| acc ^ u32::from_le_bytes(c.try_into().unwrap()));
+    data[0x1fc..0x200].copy_from_slice(&checksum.to_le_bytes());
+}
+
+/// appends a new hive bin, which contains the allocated `cells`, to the hive in `data`
+/// and returns the offsets of these cells
+fn append_hbin(data: &mut Vec<u8>, cells: &[Vec<u8>]) -> Vec<Offset> {
+    let hbin_offset = u32::from_le_bytes(data[0x28..0x2c].try_into().unwrap());
+    assert_eq!(data.len(), 0x1000 + hbin_offset as usize);
+
+    let mut hbin = b"hbin".to_vec();
+    hbin.extend(hbin_offset.to_le_bytes());
+    hbin.extend([0; 24]); // size, reserved, timestamp and spare
+
+    let mut offsets = Vec::new();
+    for cell in cells {
+        let size = (cell.len() + 4).next_multiple_of(8);
+        offsets.push(Offset(hbin_offset + hbin.len() as u32));
+        hbin.extend((-(size as i32)).to_le_bytes());
+        hbin.extend(cell);
+        hbin.resize(hbin.len() + size - 4 - cell.len(), 0);
+    }
+
+    // the remaining space is an unallocated cell
+    let hbin_size = (hbin.len() + 8).next_multiple_of(0x1000);
+    hbin.extend(((hbin_size - hbin.len()) as i32).to_le_bytes());
+    hbin.resize(hbin_size, 0);
+    hbin[8..12].copy_from_slice(&(hbin_size as u32).to_le_bytes());
+
+    data.extend(hbin);
+    data[0x28..0x2c].copy_from_slice(&(hbin_offset + hbin_size as u32).to_le_bytes());
+    update_checksum(data);
+    offsets
+}
+
+/// creates the content of a value cell with a compressed name
+fn vk_cell(name: &str, data_type: u32, data_size: u32, offset_or_data: u32) -> Vec<u8> {
+    let mut vk = b"vk".to_vec();
+    vk.extend((name.len() as u16).to_le_bytes());
+    vk.extend(data_size.to_le_bytes());
+    vk.extend(offset_or_data.to_le_bytes());
+    vk.extend(data_type.to_le_bytes());
+    vk.extend(1u16.to_le_bytes()); // VALUE_COMP_NAME
+    vk.extend(0u16.to_le_bytes());
+    vk.extend(name.as_bytes());
+    vk
+}
+
+#[test]
+fn test_no_big_data_before_1_4() {
+    // read the same hive again, but pretend that this is a hive of version 1.3
+    let mut hive = Hive::<_, CleanHive>::new(
+        testhive_with_minor_version(3),
+        HiveParseMode::NormalWithBaseBlock,
+    )
+    .unwrap();
+    let root_key = hive.root_key_node().unwrap();
+    let big_data_test = root_key
+        .subpath("big-data-test", &mut hive)
+        .unwrap()
+        .unwrap();
+    let big_data_test = big_data_test.borrow();
+    let value = big_data_test
+        .values()
+        .iter()
+        .find(|v| v.name() == "C")
+        .unwrap();
+    assert!(value.is_invalid());
+    assert_eq!(
+        value.data_provenance().cells()[0].role(),
+        DataCellRole::Data
+    );
+}
+
+#[test]
+fn test_key_node_metadata() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    assert!(root_key.flags().contains(KeyNodeFlags::KEY_COMP_NAME));
+
+    // "with-two-levels-of-subkeys" consists of 26 characters
+    let subpath_test = root_key
+        .subpath("subpath-test", &mut hive)
+        .unwrap()
+        .unwrap();
+    assert_eq!(subpath_test.borrow().max_subkey_name_length(), 26 * 2);
+    assert!(subpath_test
+        .borrow()
+        .virtualization_control_flags()
+        .is_empty());
+    assert_eq!(subpath_test.borrow().user_flags(), 0);
+    assert_eq!(subpath_test.borrow().debug(), 0);
+
+    let data_test = root_key.subpath("data-test", &mut hive).unwrap().unwrap();
+    let data_test = data_test.borrow();
+    assert_eq!(data_test.values_count(), 8);
+    let max_value_name = data_test
+        .values()
+        .iter()
+        .map(|v| v.name().encode_utf16().count() * 2)
+        .max()
+        .unwrap();
+    assert_eq!(data_test.max_value_name_length() as usize, max_value_name);
+    let max_value_data = data_test
+        .values()
+        .iter()
+        .map(|v| v.data_size())
+        .max()
+        .unwrap();
+    assert_eq!(data_test.max_value_data_size(), max_value_data);
+}
+
+/// returns the position of the key node at `path` in the file of testhive
+fn key_node_position(path: &str) -> usize {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key.subpath(path, &mut hive).unwrap().unwrap();
+    let offset = key.borrow().offset();
+    0x1000 + offset.0 as usize + 4
+}
+
+/// returns a layered hive, which consists of testhive (with a class name for `data-test`)
+/// and a layer on top of it, where
+///  - `big-data-test` is a tombstone,
+///  - `data-test` supersedes the values of the lower layer and has only one value,
+///    but inherits its class name,
+///  - `subpath-test` supersedes the whole tree of the lower layer and has no subkeys
+fn testhive_with_layer() -> LayeredHive<Cursor<Vec<u8>>> {
+    let mut data = testhive_with_minor_version(6).into_inner();
+    let set_access_bits = |data: &mut Vec<u8>, path: &str, access_bits: u32| {
+        let nk = key_node_position(path);
+        data[nk + 0x0c..nk + 0x10].copy_from_slice(&access_bits.to_le_bytes());
+    };
+
+    set_access_bits(&mut data, "big-data-test", 0x0100);
+
+    set_access_bits(&mut data, "data-test", 0x8200);
+    let nk = key_node_position("data-test");
+    data[nk + 0x24..nk + 0x28].copy_from_slice(&1u32.to_le_bytes());
+
+    set_access_bits(&mut data, "subpath-test", 0x0300);
+    let nk = key_node_position("subpath-test");
+    data[nk + 0x14..nk + 0x18].copy_from_slice(&0u32.to_le_bytes());
+
+    let layer = Hive::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap();
+    let mut hive = LayeredHive::new(testhive_with_class_name(14, false));
+    hive.push_layer(layer);
+    hive
+}
+
+#[test]
+fn test_layered_hive() {
+    let mut hive = testhive_with_layer();
+    assert_eq!(hive.layer_count(), 2);
+
+    let root_key = hive.root_key().unwrap().unwrap();
+    let names: Vec<_> = hive
+        .subkeys(&root_key)
+        .unwrap()
+        .iter()
+        .map(|k| k.name().to_string())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "character-encoding-test",
+            "data-test",
+            "subkey-test",
+            "subpath-test"
+        ]
+    );
+
+    // the tombstone hides the key of the lower layer
+    assert!(hive.subpath("big-data-test").unwrap().is_none());
+
+    // only the values of the upper layer are visible, but the class name is inherited
+    let data_test = hive.subpath("data-test").unwrap().unwrap();
+    assert_eq!(data_test.key_nodes().len(), 2);
+    assert_eq!(data_test.values().len(), 1);
+    assert!(data_test.key_nodes()[1].1.borrow().inherits_class());
+    assert_eq!(
+        hive.class_name(&data_test).unwrap().as_deref(),
+        Some("sz-test")
+    );
+
+    // the subkeys of the lower layer are hidden
+    let subpath_test = hive.subpath("subpath-test").unwrap().unwrap();
+    assert_eq!(subpath_test.key_nodes().len(), 1);
+    assert!(hive.subkeys(&subpath_test).unwrap().is_empty());
+    assert!(hive
+        .subpath("subpath-test\\with-two-levels-of-subkeys")
+        .unwrap()
+        .is_none());
+
+    // keys without layer semantics are merged
+    let subkey_test = hive.subpath("subkey-test").unwrap().unwrap();
+    assert_eq!(subkey_test.key_nodes().len(), 2);
+    assert!(!subkey_test.key_nodes()[1].1.borrow().inherits_class());
+    assert_eq!(hive.class_name(&subkey_test).unwrap(), None);
+}
+
+/// returns testhive, where `data-test` has only one value with the UTF-16 name `name`
+fn testhive_with_value_name(
+    name: &str,
+    name_comparison: NameComparison,
+) -> Hive<Cursor<Vec<u8>>, CleanHive> {
+    let mut data = testhive_with_minor_version(6).into_inner();
+    let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let mut vk = vk_cell("", 4, 0x8000_0004, 0);
+    vk[2..4].copy_from_slice(&(name.len() as u16).to_le_bytes());
+    vk[0x10..0x12].copy_from_slice(&0u16.to_le_bytes()); // not VALUE_COMP_NAME
+    vk.extend(name);
+    let vk_offset = append_hbin(&mut data, &[vk])[0];
+    let values_list_offset = append_hbin(&mut data, &[vk_offset.0.to_le_bytes().to_vec()])[0];
+
+    let nk = key_node_position("data-test");
+    data[nk + 0x24..nk + 0x28].copy_from_slice(&1u32.to_le_bytes());
+    data[nk + 0x28..nk + 0x2c].copy_from_slice(&values_list_offset.0.to_le_bytes());
+    let mut hive = Hive::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap();
+    hive.set_name_comparison(name_comparison);
+    hive
+}
+
+#[test]
+fn test_layered_value_names() {
+    // the Kelvin sign and 'k' have the same lower case form, but different upper case forms
+    for (name_comparison, count) in [(NameComparison::NtUpcase, 2), (NameComparison::Unicode, 1)] {
+        let mut hive = LayeredHive::new(testhive_with_value_name("k", name_comparison));
+        hive.push_layer(testhive_with_value_name("\u{212a}", name_comparison));
+        let data_test = hive.subpath("data-test").unwrap().unwrap();
+        let names: Vec<_> = data_test
+            .values()
+            .iter()
+            .map(|v| v.name().to_string())
+            .collect();
+        assert_eq!(names.len(), count, "{name_comparison:?}: {names:?}");
+        assert_eq!(names[0], "\u{212a}");
+    }
+}
+
+#[test]
+fn test_legacy_versions() {
+    for minor_version in 0..=6 {
+        let mut hive = Hive::<_, CleanHive>::new(
+            testhive_with_minor_version(minor_version),
+            HiveParseMode::NormalWithBaseBlock,
+        )
+        .unwrap();
+        assert_eq!(hive.parse_context().minor_version(), minor_version);
+        let root_key = hive.root_key_node().unwrap();
+        assert_eq!(root_key.subkeys(&mut hive).unwrap().len(), 5);
+    }
+}
+
+/// `legacyhive` is a hive of version 1.2, whose root key refers to its subkeys
+/// using an index root and two index leafs. The key `Values` contains a value
+/// which is larger than a big data segment, but is stored in a single cell.
+/// All names are stored as UTF-16.
+#[test]
+fn test_legacy_hive() {
+    let mut hive_path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    hive_path.push("tests");
+    hive_path.push("data");
+    hive_path.push("legacyhive");
+    let mut hive = Hive::<_, CleanHive>::new(
+        File::open(&hive_path).unwrap(),
+        HiveParseMode::NormalWithBaseBlock,
+    )
+    .unwrap();
+    assert_eq!(hive.parse_context().minor_version(), 2);
+
+    let root_key = hive.root_key_node().unwrap();
+    assert_eq!(root_key.name(), "LegacyRoot");
+    assert!(!root_key.is_name_compressed());
+    let subkeys: Vec<_> = root_key
+        .subkeys(&mut hive)
+        .unwrap()
+        .iter()
+        .map(|k| k.borrow().name().to_string())
+        .collect();
+    assert_eq!(subkeys, ["Key0", "Key1", "Key2", "Key3", "Key4", "Values"]);
+    assert!(root_key.verify_subkeys_lists(&mut hive).unwrap().is_empty());
+    assert!(root_key.subkey("KEY4", &mut hive).unwrap().is_some());
+
+    let owner = root_key
+        .security_descriptor(&mut hive)
+        .unwrap()
+        .security_descriptor()
+        .owner()
+        .unwrap()
+        .to_string();
+    assert_eq!(owner, "S-1-5-32-544");
+
+    let values = root_key.subkey("Values", &mut hive).unwrap().unwrap();
+    let values = values.borrow();
+    let value = |name| values.values().iter().find(|v| v.name() == name).unwrap();
+    assert!(matches!(
+        value("Small").value(),
+        RegistryValue::RegDWord(0x1234_5678)
+    ));
+    assert!(matches!(value("String").value(), RegistryValue::RegSZ(s) if s == "legacy"));
+
+    let large = value("Large");
+    assert!(!large.is_invalid());
+    assert_eq!(large.raw_data().len(), 20000);
+    assert!(large
+        .raw_data()
+        .iter()
+        .enumerate()
+        .all(|(i, b)| usize::from(*b) == i % 251));
+    let cells = large.data_provenance().cells();
+    assert_eq!(cells.len(), 1);
+    assert_eq!(cells[0].role(), DataCellRole::Data);
+}
+
+#[test]
+fn test_unsupported_list_types() {
+    // hash leafs have been introduced with version 1.5, but are read anyway
+    let mut hive = Hive::<_, CleanHive>::new(
+        testhive_with_minor_version(2),
+        HiveParseMode::NormalWithBaseBlock,
+    )
+    .unwrap();
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key
+        .subkey("character-encoding-test", &mut hive)
+        .unwrap()
+        .unwrap();
+    let key = key.borrow();
+    assert_eq!(key.subkeys(&mut hive).unwrap().len(), 4);
+    assert_eq!(
+        key.verify_subkeys_lists(&mut hive).unwrap(),
+        [SubkeysListIssue::UnsupportedListType {
+            list_offset: key.subkeys_list_offset(),
+            minor_version: 2
+        }]
+    );
+}
+
+#[test]
+fn test_unsupported_version() {
+    let result = Hive::<_, CleanHive>::new(
+        testhive_with_minor_version(7),
+        HiveParseMode::NormalWithBaseBlock,
+    );
+    let error = match result {
+        Ok(_) => panic!("version 1.7 should not be supported"),
+        Err(why) => why,
+    };
+    assert!(matches!(
+        error,
+        Error::UnsupportedVersion(UnsupportedVersion { major: 1, minor: 7 })
+    ));
+
+    let mut data = testhive_with_minor_version(5).into_inner();
+    data[0x14..0x18].copy_from_slice(&2u32.to_le_bytes());
+    update_checksum(&mut data);
+    let result = Hive::<_, CleanHive>::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock);
+    assert!(matches!(
+        result,
+        Err(Error::UnsupportedVersion(UnsupportedVersion {
+            major: 2,
+            minor: 5
+        }))
+    ));
+}
+
+#[test]
+fn test_offline_registry() {
+    let hive = testhive();
+    let base_block = hive.base_block().unwrap();
+    assert!(base_block.is_offline_registry());
+    assert_eq!(base_block.offline_registry_flags(), Some(1));
+    assert_eq!(
+        base_block.serialization_timestamp().unwrap().to_rfc3339(),
+        "2021-07-28T16:33:34.362703+00:00"
+    );
+    assert!(!base_block.has_valid_guids());
+    assert!(hive.is_checksum_valid().unwrap());
+}
+
+fn testhive_with_corrupted_base_block() -> Cursor<Vec<u8>> {
+    let mut data = testhive_with_minor_version(5).into_inner();
+    data[0x1fc] ^= 0xff;
+    Cursor::new(data)
+}
+
+#[test]
+fn test_base_block_recovery() {
+    assert!(Hive::<_, CleanHive>::new(
+        testhive_with_corrupted_base_block(),
+        HiveParseMode::NormalWithBaseBlock
+    )
+    .is_err());
+
+    let mut hive = Hive::<_, CleanHive>::new(
+        testhive_with_corrupted_base_block(),
+        HiveParseMode::NormalWithBaseBlockRecovery(None),
+    )
+    .unwrap();
+    assert!(hive.base_block_error().is_some());
+    assert!(hive.base_block().is_none());
+    let root_key = hive.root_key_node().unwrap();
+    assert_eq!(root_key.name(), "ROOT");
+    assert_eq!(root_key.subkeys(&mut hive).unwrap().len(), 5);
+
+    let backup = testhive().base_block().unwrap().clone();
+    let mut hive = Hive::<_, CleanHive>::new(
+        testhive_with_corrupted_base_block(),
+        HiveParseMode::NormalWithBaseBlockRecovery(Some(Box::new(backup))),
+    )
+    .unwrap();
+    assert!(hive.base_block_error().is_some());
+    assert_eq!(
+        hive.last_written_timestamp(),
+        testhive().last_written_timestamp()
+    );
+    let root_key = hive.root_key_node().unwrap();
+    assert_eq!(root_key.subkeys(&mut hive).unwrap().len(), 5);
+
+    let hive = Hive::<_, CleanHive>::new(
+        testhive_with_minor_version(5),
+        HiveParseMode::NormalWithBaseBlockRecovery(None),
+    )
+    .unwrap();
+    assert!(hive.base_block_error().is_none());
+}
+
+#[test]
+fn test_hivebin_headers() {
+    let mut hive = testhive();
+    let data_size = *hive.base_block().unwrap().data_size();
+
+    let headers = hive.hivebin_headers().unwrap();
+    assert!(!headers.is_empty());
+    assert_eq!(headers[0].offset().0, 0);
+    let sizes: u32 = headers.iter().map(|hb| *hb.size()).sum();
+    assert_eq!(sizes, data_size);
+    for (a, b) in headers.iter().zip(headers.iter().skip(1)) {
+        assert_eq!(a.offset().0 + a.size(), b.offset().0);
+    }
+    let last = headers.last().unwrap().clone();
+
+    // the hive must still be usable
+    let root_cell_offset = hive.root_cell_offset().unwrap();
+    let root_key = hive.root_key_node().unwrap();
+    assert_eq!(root_key.subkeys(&mut hive).unwrap().len(), 5);
+
+    let hivebin = hive.hbin_containing(root_cell_offset).unwrap().unwrap();
+    assert_eq!(hivebin.offset().0, 0);
+    assert!(hivebin.contains(root_cell_offset));
+
+    let offset = Offset(last.offset().0 + last.size() - 1);
+    let hivebin = hive.hbin_containing(offset).unwrap().unwrap();
+    assert_eq!(hivebin.offset(), last.offset());
+    assert!(hive
+        .hbin_containing(Offset(last.offset().0 + last.size()))
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_key_and_value_offsets() {
+    let mut hive = testhive();
+    let root_cell_offset = hive.root_cell_offset().unwrap();
+    let root_key = hive.root_key_node().unwrap();
+    assert_eq!(root_key.offset(), root_cell_offset);
+
+    let key = root_key.subpath("data-test", &mut hive).unwrap().unwrap();
+    let key_offset = key.borrow().offset();
+    assert_ne!(key_offset, root_cell_offset);
+
+    let same_key = hive.key_at(key_offset).unwrap();
+    assert_eq!(same_key.offset(), key_offset);
+    assert_eq!(same_key.name(), "data-test");
+    assert_eq!(same_key.values().len(), key.borrow().values().len());
+
+    for value in key.borrow().values() {
+        let same_value = hive.value_at(value.offset()).unwrap();
+        assert_eq!(same_value.offset(), value.offset());
+        assert_eq!(same_value.name(), value.name());
+        assert_eq!(same_value.raw_data(), value.raw_data());
+    }
+
+    assert!(hive.value_at(key_offset).is_err());
+}
+
+#[test]
+fn test_key_path() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    assert!(root_key.parent_node(&mut hive).unwrap().is_none());
+    let root_path = root_key.path(&mut hive);
+    assert!(root_path.is_complete());
+    assert_eq!(root_path.components(), &["ROOT"]);
+    assert_eq!(root_path.relative_to_root().unwrap(), "");
+
+    let key = root_key
+        .subpath(
+            "subpath-test\\with-two-levels-of-subkeys\\subkey1",
+            &mut hive,
+        )
+        .unwrap()
+        .unwrap();
+    let path = key.borrow().path(&mut hive);
+    assert_eq!(path.termination(), PathTermination::Root);
+    assert_eq!(
+        path.to_string(),
+        "ROOT\\subpath-test\\with-two-levels-of-subkeys\\subkey1"
+    );
+    assert_eq!(
+        path.relative_to_root().unwrap(),
+        "subpath-test\\with-two-levels-of-subkeys\\subkey1"
+    );
+
+    let parent = key.borrow().parent_node(&mut hive).unwrap().unwrap();
+    assert_eq!(parent.name(), "with-two-levels-of-subkeys");
+    assert_eq!(parent.offset(), key.borrow().parent);
+}
+
+#[test]
+fn test_key_path_with_damaged_parents() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key.subpath("data-test", &mut hive).unwrap().unwrap();
+    let key_offset = key.borrow().offset();
+
+    // the parent of a key must be a key node
+    let mut key = hive.key_at(key_offset).unwrap();
+    let value_offset = key.values()[0].offset();
+    key.parent = value_offset;
+    let path = key.path(&mut hive);
+    assert_eq!(
+        path.termination(),
+        PathTermination::UnreachableParent(value_offset)
+    );
+    assert_eq!(path.components(), &["data-test"]);
+    assert!(path.relative_to_root().is_none());
+
+    // a key must not be its own parent
+    key.parent = key_offset;
+    let path = key.path(&mut hive);
+    assert_eq!(path.termination(), PathTermination::Loop(key_offset));
+    assert_eq!(path.to_string(), "...\\data-test");
+}
+
+#[test]
+fn test_subkey_lookup() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key.subkey("subkey-test", &mut hive).unwrap().unwrap();
+    let key_offset = key.borrow().offset();
+    let subkeys: Vec<_> = key
+        .borrow()
+        .subkeys(&mut hive)
+        .unwrap()
+        .iter()
+        .map(|sk| (sk.borrow().name().to_string(), sk.borrow().offset()))
+        .collect();
+    assert_eq!(subkeys.len(), 512);
+
+    // use a key node whose subkeys have not been read yet
+    let key = hive.key_at(key_offset).unwrap();
+    for (name, offset) in subkeys {
+        let subkey = key.subkey(&name, &mut hive).unwrap().unwrap();
+        assert_eq!(subkey.borrow().offset(), offset);
+        let subkey = key
+            .subkey(&name.to_uppercase(), &mut hive)
+            .unwrap()
+            .unwrap();
+        assert_eq!(subkey.borrow().offset(), offset);
+    }
+    assert!(key.subkey("does-not-exist", &mut hive).unwrap().is_none());
+}
+
+#[test]
+fn test_name_comparison() {
+    let mut hive = testhive();
+    assert_eq!(hive.name_comparison(), NameComparison::NtUpcase);
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key
+        .subkey("CHARACTER-ENCODING-TEST", &mut hive)
+        .unwrap()
+        .unwrap();
+    let key_offset = key.borrow().offset();
+
+    for (name, expected) in [
+        ("ÄÖÜ", "äöü"),
+        ("ａ", "Ａ"),
+        // Windows does not change the case of characters outside of the BMP,
+        // so these are two different keys
+        ("\u{10410}", "\u{10410}"),
+        ("\u{10438}", "\u{10438}"),
+    ] {
+        // once without and once with cached subkeys
+        let key_node = hive.key_at(key_offset).unwrap();
+        let subkey = key_node.subkey(name, &mut hive).unwrap().unwrap();
+        assert_eq!(subkey.borrow().name(), expected);
+        let subkey = key.borrow().subkey(name, &mut hive).unwrap().unwrap();
+        assert_eq!(subkey.borrow().name(), expected);
+    }
+
+    hive.set_name_comparison(NameComparison::Unicode);
+    let key_node = hive.key_at(key_offset).unwrap();
+    let subkey = key_node.subkey("\u{10438}", &mut hive).unwrap().unwrap();
+    assert_eq!(subkey.borrow().name(), "\u{10410}");
+    assert!(root_key
+        .subpath("character-encoding-test\\ÄÖÜ", &mut hive)
+        .unwrap()
+        .is_some());
+}
+
+fn collect_subkeys_list_issues(
+    key: &KeyNode,
+    hive: &mut Hive<File, CleanHive>,
+) -> Vec<SubkeysListIssue> {
+    let mut issues = key.verify_subkeys_lists(hive).unwrap();
+    for subkey in key.subkeys(hive).unwrap().iter() {
+        issues.extend(collect_subkeys_list_issues(&subkey.borrow(), hive));
+    }
+    issues
+}
+
+#[test]
+fn test_verify_subkeys_lists() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    assert_eq!(collect_subkeys_list_issues(&root_key, &mut hive), vec![]);
+
+    let root_cell_offset = hive.root_cell_offset().unwrap().0 as usize;
+    let list_offset = root_key.subkeys_list_offset();
+    let mut data = testhive_with_minor_version(5).into_inner();
+
+    // swap the first two entries of the hash leaf of the root key
+    let items = 0x1000 + list_offset.0 as usize + 8;
+    let first: Vec<u8> = data[items..items + 8].to_vec();
+    data.copy_within(items + 8..items + 16, items);
+    data[items + 8..items + 16].copy_from_slice(&first);
+
+    // break the hash of the third entry
+    data[items + 20] ^= 0xff;
+
+    // change the number of subkeys
+    data[0x1000 + root_cell_offset + 24] = 6;
+
+    let mut hive =
+        Hive::<_, CleanHive>::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap();
+    let root_key = hive.root_key_node().unwrap();
+    let issues = root_key.verify_subkeys_lists(&mut hive).unwrap();
+    assert_eq!(issues.len(), 3, "{issues:?}");
+    assert!(matches!(
+        &issues[0],
+        SubkeysListIssue::OutOfOrder { previous_name, name, .. }
+            if previous_name == "character-encoding-test" && name == "big-data-test"
+    ));
+    assert!(matches!(
+        &issues[1],
+        SubkeysListIssue::WrongHash { name, .. } if name == "data-test"
+    ));
+    assert_eq!(
+        issues[2],
+        SubkeysListIssue::WrongCount {
+            declared: 6,
+            found: 5
+        }
+    );
+
+    // the subkeys are found nevertheless
+    assert_eq!(root_key.subkeys(&mut hive).unwrap().len(), 5);
+
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let subkey_offset = |name: &str, hive: &mut Hive<File, CleanHive>| {
+        let subkey = root_key.subkey(name, hive).unwrap().unwrap();
+        let offset = subkey.borrow().offset();
+        offset
+    };
+    let big_data_test = subkey_offset("big-data-test", &mut hive);
+    let character_encoding_test = subkey_offset("character-encoding-test", &mut hive);
+    let data_test = root_key.subkey("data-test", &mut hive).unwrap().unwrap();
+    let value_offset = data_test.borrow().values()[0].offset();
+
+    // create a copy of the key node of `big-data-test`
+    let mut data = testhive_with_minor_version(5).into_inner();
+    let cell = 0x1000 + big_data_test.0 as usize;
+    let size = i32::from_le_bytes(data[cell..cell + 4].try_into().unwrap()).unsigned_abs();
+    let copy = data[cell + 4..cell + size as usize].to_vec();
+    let big_data_test_copy = append_hbin(&mut data, &[copy])[0];
+
+    // replace the subkeys list of the root key by a fast leaf
+    let mut fast_leaf = b"lf".to_vec();
+    fast_leaf.extend(5u16.to_le_bytes());
+    for (offset, hint) in [
+        (big_data_test, b"big-"),
+        (big_data_test_copy, b"big-"),
+        (character_encoding_test, b"char"),
+        (character_encoding_test, b"char"),
+        (value_offset, b"vk\0\0"),
+    ] {
+        fast_leaf.extend(offset.0.to_le_bytes());
+        fast_leaf.extend(hint);
+    }
+    // break the hint of the third entry
+    fast_leaf[4 + 2 * 8 + 4 + 3] = b'x';
+
+    let fast_leaf_offset = append_hbin(&mut data, &[fast_leaf])[0];
+    let nk = 0x1000 + root_cell_offset + 4;
+    data[nk + 0x14..nk + 0x18].copy_from_slice(&5u32.to_le_bytes());
+    data[nk + 0x1c..nk + 0x20].copy_from_slice(&fast_leaf_offset.0.to_le_bytes());
+
+    let mut hive =
+        Hive::<_, CleanHive>::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap();
+    let root_key = hive.root_key_node().unwrap();
+    let issues = root_key.verify_subkeys_lists(&mut hive).unwrap();
+    assert_eq!(issues.len(), 4, "{issues:?}");
+    assert_eq!(
+        issues[0],
+        SubkeysListIssue::DuplicateName {
+            list_offset: fast_leaf_offset,
+            key_node_offset: big_data_test_copy,
+            name: "big-data-test".to_string(),
+        }
+    );
+    assert_eq!(
+        issues[1],
+        SubkeysListIssue::WrongHint {
+            list_offset: fast_leaf_offset,
+            key_node_offset: character_encoding_test,
+            name: "character-encoding-test".to_string(),
+            stored: *b"chax",
+        }
+    );
+    assert_eq!(
+        issues[2],
+        SubkeysListIssue::DuplicateOffset {
+            list_offset: fast_leaf_offset,
+            key_node_offset: character_encoding_test,
+        }
+    );
+    assert!(matches!(
+        &issues[3],
+        SubkeysListIssue::UnreadableSubkey { list_offset, key_node_offset, .. }
+            if *list_offset == fast_leaf_offset && *key_node_offset == value_offset
+    ));
+}
+
+/// returns a hive where the subkeys list of `subkey-test` is an index root which
+/// refers to `target`
+fn testhive_with_nested_index_root(target: Offset) -> Hive<Cursor<Vec<u8>>, CleanHive> {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key.subkey("subkey-test", &mut hive).unwrap().unwrap();
+    let key_offset = key.borrow().offset().0 as usize;
+
+    // we overwrite the data of some value with the new index root
+    let big_data_test = root_key
+        .subkey("big-data-test", &mut hive)
+        .unwrap()
+        .unwrap();
+    let new_list_offset = big_data_test.borrow().values()[0].data_offset().unwrap();
+
+    let mut data = testhive_with_minor_version(5).into_inner();
+    let new_list = 0x1000 + new_list_offset.0 as usize + 4;
+    data[new_list..new_list + 4].copy_from_slice(b"ri\x01\x00");
+    data[new_list + 4..new_list + 8].copy_from_slice(&target.0.to_le_bytes());
+
+    let subkeys_list_offset = 0x1000 + key_offset + 4 + 28;
+    data[subkeys_list_offset..subkeys_list_offset + 4]
+        .copy_from_slice(&new_list_offset.0.to_le_bytes());
+
+    Hive::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap()
+}
+
+#[test]
+fn test_nested_index_roots() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key.subkey("subkey-test", &mut hive).unwrap().unwrap();
+    let original_list_offset = key.borrow().subkeys_list_offset();
+    let new_list_offset = root_key
+        .subkey("big-data-test", &mut hive)
+        .unwrap()
+        .unwrap()
+        .borrow()
+        .values()[0]
+        .data_offset()
+        .unwrap();
+
+    // an index root which refers to another index root
+    let mut hive = testhive_with_nested_index_root(original_list_offset);
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key.subkey("subkey-test", &mut hive).unwrap().unwrap();
+    assert_eq!(key.borrow().subkeys(&mut hive).unwrap().len(), 512);
+    assert_eq!(
+        key.borrow().verify_subkeys_lists(&mut hive).unwrap(),
+        vec![]
+    );
+
+    hive.set_max_subkeys_list_depth(1);
+    let key = hive.key_at(key.borrow().offset()).unwrap();
+    let err = match key.subkeys(&mut hive) {
+        Ok(_) => panic!("expected an error"),
+        Err(why) => why,
+    };
+    assert!(matches!(
+        err,
+        Error::SubkeysList(SubkeysListError::TooDeep { offset, max_depth: 1 })
+            if offset == original_list_offset
+    ));
+
+    // an index root which refers to itself
+    let mut hive = testhive_with_nested_index_root(new_list_offset);
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key.subkey("subkey-test", &mut hive).unwrap().unwrap();
+    let err = match key.borrow().subkeys(&mut hive) {
+        Ok(_) => panic!("expected an error"),
+        Err(why) => why,
+    };
+    assert!(matches!(
+        err,
+        Error::SubkeysList(SubkeysListError::Cycle { offset }) if offset == new_list_offset
+    ));
+
+    // a chain of index roots, where every index root refers twice to the next one
+    let mut data = testhive_with_minor_version(5).into_inner();
+    let mut target = original_list_offset;
+    for _ in 0..40 {
+        let mut index_root = b"ri".to_vec();
+        index_root.extend(2u16.to_le_bytes());
+        index_root.extend(target.0.to_le_bytes());
+        index_root.extend(target.0.to_le_bytes());
+        target = append_hbin(&mut data, &[index_root])[0];
+    }
+    let nk = key_node_position("subkey-test");
+    data[nk + 0x1c..nk + 0x20].copy_from_slice(&target.0.to_le_bytes());
+
+    let mut hive =
+        Hive::<_, CleanHive>::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap();
+    hive.set_max_subkeys_list_depth(64);
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key.subkey("subkey-test", &mut hive).unwrap().unwrap();
+    assert_eq!(key.borrow().subkeys(&mut hive).unwrap().len(), 512);
+
+    // every list is read only once, but all repeated references are reported
+    let issues = key.borrow().verify_subkeys_lists(&mut hive).unwrap();
+    assert_eq!(issues.len(), 40);
+    assert_eq!(
+        issues[0],
+        SubkeysListIssue::DuplicateList {
+            list_offset: original_list_offset
+        }
+    );
+}
+
+#[test]
+fn test_codepage() {
+    let mut hive = testhive();
+    assert_eq!(hive.codepage(), Codepage::Latin1);
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key
+        .subkey("character-encoding-test", &mut hive)
+        .unwrap()
+        .unwrap();
+    let key_offset = key.borrow().offset();
+
+    let subkey = key.borrow().subkey("äöü", &mut hive).unwrap().unwrap();
+    let subkey_offset = subkey.borrow().offset();
+    assert!(subkey.borrow().is_name_compressed());
+    assert_eq!(subkey.borrow().raw_name(), &[0xe4, 0xf6, 0xfc]);
+
+    let subkey = key.borrow().subkey("Ａ", &mut hive).unwrap().unwrap();
+    assert!(!subkey.borrow().is_name_compressed());
+    assert_eq!(subkey.borrow().raw_name(), &[0x21, 0xff]);
+
+    hive.set_codepage(Codepage::Windows1251);
+    let subkey = hive.key_at(subkey_offset).unwrap();
+    assert_eq!(subkey.name(), "дць");
+    assert_eq!(subkey.raw_name(), &[0xe4, 0xf6, 0xfc]);
+    assert_eq!(subkey.name_utf16(), vec![0xe4, 0xf6, 0xfc]);
+    assert!(!subkey.is_name_lossy());
+    let key = hive.key_at(key_offset).unwrap();
+    assert!(key
+        .subkeys(&mut hive)
+        .unwrap()
+        .iter()
+        .any(|sk| sk.borrow().name() == "дць"));
+
+    // hashes, hints and the order of subkeys depend on the raw bytes of compressed names
+    let key = hive.key_at(key_offset).unwrap();
+    let subkey = key.subkey("дць", &mut hive).unwrap().unwrap();
+    assert_eq!(subkey.borrow().offset(), subkey_offset);
+    assert!(key.subkey("äöü", &mut hive).unwrap().is_none());
+    assert_eq!(key.verify_subkeys_lists(&mut hive).unwrap(), vec![]);
+
+    let data_test = root_key.subkey("data-test", &mut hive).unwrap().unwrap();
+    for value in data_test.borrow().values() {
+        assert!(value.is_name_compressed());
+        assert_eq!(value.raw_name(), value.name().as_bytes());
+    }
+}
+
+/// replaces the hash of the entry for `key_node_offset` in the hash leaf at `list_offset`
+fn set_name_hash(data: &mut [u8], list_offset: Offset, key_node_offset: Offset, hash: u32) {
+    let list = 0x1000 + list_offset.0 as usize + 4;
+    assert_eq!(&data[list..list + 2], b"lh");
+    let count = u16::from_le_bytes(data[list + 2..list + 4].try_into().unwrap()) as usize;
+    let entry = (0..count)
+        .map(|i| list + 4 + i * 8)
+        .find(|e| data[*e..*e + 4] == key_node_offset.0.to_le_bytes())
+        .unwrap();
+    data[entry + 4..entry + 8].copy_from_slice(&hash.to_le_bytes());
+}
+
+#[test]
+fn test_compressed_names_with_undefined_bytes() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key
+        .subkey("character-encoding-test", &mut hive)
+        .unwrap()
+        .unwrap();
+    let key_offset = key.borrow().offset();
+    let list_offset = key.borrow().subkeys_list_offset();
+    let subkey = key.borrow().subkey("äöü", &mut hive).unwrap().unwrap();
+    let subkey_offset = subkey.borrow().offset();
+    let data_test = root_key.subkey("data-test", &mut hive).unwrap().unwrap();
+    let value_offset = data_test.borrow().values()[0].offset();
+    let value_name = data_test.borrow().values()[0].name().to_string();
+
+    // 0xd2 is not defined in Windows-1253
+    let mut data = testhive_with_minor_version(5).into_inner();
+    let name = 0x1000 + subkey_offset.0 as usize + 4 + 0x4c;
+    assert_eq!(&data[name..name + 3], &[0xe4, 0xf6, 0xfc]);
+    data[name] = 0xd2;
+    set_name_hash(
+        &mut data,
+        list_offset,
+        subkey_offset,
+        (0xd2 * 37 + 0xd6) * 37 + 0xdc,
+    );
+    let name = 0x1000 + value_offset.0 as usize + 4 + 0x14;
+    assert_eq!(data[name], value_name.as_bytes()[0]);
+    data[name] = 0xd2;
+
+    let mut hive =
+        Hive::<_, CleanHive>::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap();
+    let subkey = hive.key_at(subkey_offset).unwrap();
+    assert_eq!(subkey.name(), "Òöü");
+    assert_eq!(subkey.name_utf16(), vec![0xd2, 0xf6, 0xfc]);
+    assert!(!subkey.is_name_lossy());
+
+    hive.set_codepage(Codepage::Windows1253);
+    let subkey = hive.key_at(subkey_offset).unwrap();
+    assert_eq!(subkey.name(), "\u{fffd}φό");
+    assert_eq!(subkey.name_utf16(), vec![0xd2, 0xf6, 0xfc]);
+    assert!(subkey.is_name_lossy());
+
+    // the key can be found by its raw name, but not by its decoded name
+    let key = hive.key_at(key_offset).unwrap();
+    let found = key
+        .subkey_utf16(&subkey.name_utf16(), &mut hive)
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.borrow().offset(), subkey_offset);
+    assert!(key.subkey("\u{fffd}φό", &mut hive).unwrap().is_none());
+
+    let value = hive.value_at(value_offset).unwrap();
+    assert!(value.is_name_compressed());
+    assert!(value.is_name_lossy());
+    assert_eq!(value.name_utf16()[0], 0xd2);
+    assert_eq!(
+        value.name_utf16()[1..],
+        value_name.encode_utf16().collect::<Vec<_>>()[1..]
+    );
+
+    // names with defined bytes are decoded without loss
+    let key = hive.key_at(key_offset).unwrap();
+    for subkey in key.subkeys(&mut hive).unwrap().iter() {
+        if subkey.borrow().offset() != subkey_offset {
+            assert!(!subkey.borrow().is_name_lossy());
+        }
+    }
+}
+
+#[test]
+fn test_names_with_unpaired_surrogates() {
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key
+        .subkey("character-encoding-test", &mut hive)
+        .unwrap()
+        .unwrap();
+    let list_offset = key.borrow().subkeys_list_offset();
+    let subkey = key.borrow().subkey("Ａ", &mut hive).unwrap().unwrap();
+    let subkey_offset = subkey.borrow().offset();
+    assert!(!subkey.borrow().is_name_lossy());
+
+    // replace the name by a single high surrogate, and fix the hash in the lh list
+    let mut data = testhive_with_minor_version(5).into_inner();
+    let name = 0x1000 + subkey_offset.0 as usize + 4 + 0x4c;
+    assert_eq!(&data[name..name + 2], &[0x21, 0xff]);
+    data[name..name + 2].copy_from_slice(&[0x00, 0xd8]);
+
+    set_name_hash(&mut data, list_offset, subkey_offset, 0xd800);
+
+    let mut hive = Hive::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap();
+    let subkey = hive.key_at(subkey_offset).unwrap();
+    assert!(subkey.is_name_lossy());
+    assert_eq!(subkey.raw_name(), &[0x00, 0xd8]);
+    assert_eq!(subkey.name_utf16(), vec![0xd800]);
+    assert_eq!(subkey.name(), "\u{fffd}");
+
+    let root_key = hive.root_key_node().unwrap();
+    let key = root_key
+        .subkey("character-encoding-test", &mut hive)
+        .unwrap()
+        .unwrap();
+    let found = key
+        .borrow()
+        .subkey_utf16(&[0xd800], &mut hive)
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.borrow().offset(), subkey_offset);
+    assert!(key
+        .borrow()
+        .subkey("\u{fffd}", &mut hive)
+        .unwrap()
+        .is_none());
+
+    let path: Vec<Vec<u16>> = vec![
+        "character-encoding-test".encode_utf16().collect(),
+        vec![0xd800],
+    ];
+    let found = root_key.subpath(&path[..], &mut hive).unwrap().unwrap();
+    assert_eq!(found.borrow().offset(), subkey_offset);
+
+    // all subkeys can still be read
+    assert_eq!(key.borrow().subkeys(&mut hive).unwrap().len(), 4);
+    // 0xd800 is sorted before "𐐸" (0xd801 0xdc38), but the hash is correct
+    let issues = key.borrow().verify_subkeys_lists(&mut hive).unwrap();
+    assert_eq!(issues.len(), 1);
+    assert!(matches!(
+        &issues[0],
+        SubkeysListIssue::OutOfOrder { key_node_offset, name, .. }
+            if *key_node_offset == subkey_offset && name == "\u{fffd}"
+    ));
+}
+
+#[test]
+fn test_typed_errors() {
+    assert!(matches!(
+        Hive::<_, CleanHive>::new(
+            testhive_with_corrupted_base_block(),
+            HiveParseMode::NormalWithBaseBlock
+        ),
+        Err(Error::BadChecksum { offset: 0x1fc, .. })
+    ));
+
+    let mut hive =
+        Hive::<_, CleanHive>::new(testhive_with_minor_version(5), HiveParseMode::Raw).unwrap();
+    assert!(matches!(hive.root_key_node(), Err(Error::MissingRootCell)));
+    assert!(matches!(hive.data_size(), Err(Error::MissingBaseBlock)));
+
+    let mut hive = testhive();
+    let root_key = hive.root_key_node().unwrap();
+    let value_offset = root_key
+        .subkey("data-test", &mut hive)
+        .unwrap()
+        .unwrap()
+        .borrow()
+        .values()[0]
+        .offset();
+    match hive.key_at(value_offset) {
+        Err(Error::BadMagic { found, .. }) => assert_eq!(found, b"vk"),
+        other => panic!("unexpected result: {:?}", other.map(|k| k.offset())),
+    }
+    assert!(matches!(
+        hive.key_at(Offset(0x7fff_0000)),
+        Err(Error::OffsetOutOfBounds {
+            offset: Offset(0x7fff_0000)
+        })
+    ));
+
+    // set a user flag (as stored by Windows XP) and an unknown bit in the flags of the root key
+    let root_cell_offset = hive.root_cell_offset().unwrap();
+    let mut data = testhive_with_minor_version(5).into_inner();
+    let flags = 0x1000 + root_cell_offset.0 as usize + 4 + 2;
+    data[flags + 1] |= 0x88;
+    let mut hive =
+        Hive::<_, CleanHive>::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap();
+    let root_key = hive.root_key_node().unwrap();
+    assert_eq!(root_key.raw_flags() & 0xf800, 0x8800);
+    assert_eq!(root_key.flags().bits() & 0xf800, 0);
+    assert!(root_key.flags().contains(KeyNodeFlags::KEY_COMP_NAME));
+    assert_eq!(root_key.user_flags(), 0x08);
+
+    // data which is stored in the data offset field cannot be larger than 4 bytes
+    let mut data = testhive_with_minor_version(5).into_inner();
+    let data_size = 0x1000 + value_offset.0 as usize + 4 + 4;
+    data[data_size..data_size + 4].copy_from_slice(&0x8000_0005u32.to_le_bytes());
+    let mut hive =
+        Hive::<_, CleanHive>::new(Cursor::new(data), HiveParseMode::NormalWithBaseBlock).unwrap();
+    assert!(matches!(
+        hive.value_at(value_offset),
+        Err(Error::InvalidDataSize {
+            data_size: 0x8000_0005,
+            ..
+        })
+    ));
+}